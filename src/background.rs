@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+use tsify_next::Tsify;
+use wasm_bindgen::prelude::*;
+use web_sys::CanvasRenderingContext2d;
+
+#[derive(Clone, Serialize, Deserialize, Tsify)]
+#[serde(rename = "BackgroundOptions", tag = "kind", content = "value", rename_all = "lowercase")]
+pub enum BackgroundKind {
+    Transparent,
+    Solid(String),
+    Fade(f64),
+}
+
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct Background {
+    kind: BackgroundKind,
+}
+
+#[wasm_bindgen]
+impl Background {
+    #[wasm_bindgen]
+    pub fn transparent() -> Background {
+        Background {
+            kind: BackgroundKind::Transparent,
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn solid(color: &str) -> Background {
+        Background {
+            kind: BackgroundKind::Solid(color.to_string()),
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn fade(alpha: f64) -> Background {
+        Background {
+            kind: BackgroundKind::Fade(alpha.clamp(0.0, 1.0)),
+        }
+    }
+}
+
+impl Background {
+    pub fn kind(&self) -> &BackgroundKind {
+        &self.kind
+    }
+
+    pub fn paint(&self, ctx: &CanvasRenderingContext2d) {
+        let Some(canvas) = ctx.canvas() else {
+            return;
+        };
+        let (width, height) = (canvas.width() as f64, canvas.height() as f64);
+
+        ctx.save();
+        let _ = ctx.set_transform(1.0, 0.0, 0.0, 1.0, 0.0, 0.0);
+        match self.kind {
+            BackgroundKind::Transparent => ctx.clear_rect(0.0, 0.0, width, height),
+            BackgroundKind::Solid(ref color) => {
+                ctx.set_fill_style(&JsValue::from_str(color));
+                ctx.fill_rect(0.0, 0.0, width, height);
+            }
+            BackgroundKind::Fade(alpha) => {
+                let _ = ctx.set_global_composite_operation("destination-out");
+                ctx.set_global_alpha(alpha);
+                ctx.set_fill_style(&JsValue::from_str("#000"));
+                ctx.fill_rect(0.0, 0.0, width, height);
+            }
+        }
+        ctx.restore();
+    }
+}
+
+impl From<BackgroundKind> for Background {
+    fn from(kind: BackgroundKind) -> Background {
+        match kind {
+            BackgroundKind::Transparent => Background::transparent(),
+            BackgroundKind::Solid(color) => Background::solid(&color),
+            BackgroundKind::Fade(alpha) => Background::fade(alpha),
+        }
+    }
+}
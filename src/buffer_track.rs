@@ -0,0 +1,87 @@
+use crate::events::EventCallbacks;
+use crate::ended_callback;
+use crate::looping::SharedLoop;
+use std::cell::Cell;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{AudioBuffer, AudioBufferSourceNode, AudioContext, AudioScheduledSourceNode, GainNode};
+
+// A decoded track. Buffer sources cannot be restarted, so seeking replaces the node.
+pub struct BufferTrack {
+    context: AudioContext,
+    buffer: AudioBuffer,
+    bus: GainNode,
+    looping: SharedLoop,
+    events: EventCallbacks,
+    ended: Option<Rc<Cell<bool>>>,
+    node: Option<AudioBufferSourceNode>,
+    started_at: f64,
+}
+
+impl BufferTrack {
+    pub fn new(
+        context: &AudioContext,
+        buffer: &AudioBuffer,
+        bus: GainNode,
+        looping: &SharedLoop,
+        events: &EventCallbacks,
+        ended: Option<Rc<Cell<bool>>>,
+    ) -> BufferTrack {
+        BufferTrack {
+            context: context.clone(),
+            buffer: buffer.clone(),
+            bus,
+            looping: looping.clone(),
+            events: events.clone(),
+            ended,
+            node: None,
+            started_at: 0.0,
+        }
+    }
+
+    pub fn start(&mut self, offset: f64) -> Result<(), JsValue> {
+        self.stop()?;
+
+        let node = self.context.create_buffer_source()?;
+        node.set_buffer(Some(&self.buffer));
+        node.connect_with_audio_node(&self.bus)?;
+
+        if self.looping.restart_at().is_some() {
+            node.set_loop(true);
+            if let Some((start, end)) = self.looping.ab() {
+                node.set_loop_start(start);
+                node.set_loop_end(end.min(self.buffer.duration()));
+            }
+        }
+
+        let on_ended = ended_callback(&self.events, &self.looping, None, self.ended.clone());
+        AudioScheduledSourceNode::set_onended(&node, Some(on_ended.as_ref().unchecked_ref()));
+        on_ended.forget();
+
+        let offset = offset.min(self.buffer.duration());
+        node.start_with_when_and_grain_offset(0.0, offset)?;
+
+        self.started_at = self.context.current_time() - offset;
+        self.node = Some(node);
+        Ok(())
+    }
+
+    pub fn stop(&mut self) -> Result<(), JsValue> {
+        if let Some(node) = self.node.take() {
+            AudioScheduledSourceNode::set_onended(&node, None);
+            let _ = AudioScheduledSourceNode::stop(&node);
+            node.disconnect()?;
+        }
+        Ok(())
+    }
+
+    pub fn current_time(&self) -> f64 {
+        let elapsed = self.context.current_time() - self.started_at;
+        self.looping.fold(elapsed, self.duration())
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.buffer.duration()
+    }
+}
@@ -0,0 +1,96 @@
+use crate::rng::Rng;
+use serde::{Deserialize, Serialize};
+use tsify_next::Tsify;
+use wasm_bindgen::prelude::*;
+
+const BASS_CUTOFF_HZ: f64 = 150.0;
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Serialize, Deserialize, Tsify)]
+#[serde(rename = "CameraOptions", default)]
+pub struct CameraConfig {
+    /// Extra scale applied at full bass energy.
+    pub zoom: f64,
+    /// Maximum shake offset in CSS pixels.
+    pub shake: f64,
+    pub smoothing: f64,
+    /// Bass level (0..1) below which the camera stays still.
+    pub threshold: f64,
+}
+
+#[wasm_bindgen]
+impl CameraConfig {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> CameraConfig {
+        CameraConfig {
+            zoom: 0.06,
+            shake: 4.0,
+            smoothing: 0.85,
+            threshold: 0.6,
+        }
+    }
+}
+
+impl Default for CameraConfig {
+    fn default() -> Self {
+        CameraConfig::new()
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct CameraTransform {
+    pub scale: f64,
+    pub offset_x: f64,
+    pub offset_y: f64,
+}
+
+pub struct Camera {
+    config: CameraConfig,
+    level: f64,
+    rng: Rng,
+}
+
+impl Camera {
+    pub fn new(config: CameraConfig) -> Camera {
+        Camera {
+            config,
+            level: 0.0,
+            rng: Rng::from_entropy(),
+        }
+    }
+
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+    }
+
+    pub fn config(&self) -> CameraConfig {
+        self.config
+    }
+
+    pub fn update(&mut self, spectrum: &[u8], nyquist: f64) -> CameraTransform {
+        let bins = ((BASS_CUTOFF_HZ / nyquist) * spectrum.len() as f64).ceil() as usize;
+        let bins = bins.clamp(1, spectrum.len().max(1));
+        let bass = spectrum
+            .iter()
+            .take(bins)
+            .map(|&v| v as f64)
+            .sum::<f64>()
+            / (bins as f64 * 255.0);
+
+        let threshold = self.config.threshold.clamp(0.0, 0.99);
+        let excess = ((bass - threshold) / (1.0 - threshold)).max(0.0);
+        let smoothing = self.config.smoothing.clamp(0.0, 0.99);
+        self.level = if excess > self.level {
+            excess
+        } else {
+            self.level * smoothing
+        };
+
+        let shake = self.config.shake * self.level;
+        CameraTransform {
+            scale: 1.0 + self.config.zoom * self.level,
+            offset_x: self.rng.signed() * shake,
+            offset_y: self.rng.signed() * shake,
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use wasm_bindgen::prelude::*;
+use web_sys::{AnalyserNode, AudioContext, DynamicsCompressorNode};
+
+const MIN_RMS: f64 = 0.005;
+const MIN_GAIN: f64 = 0.1;
+const MAX_GAIN: f64 = 8.0;
+const ADAPT_RATE: f64 = 0.02;
+
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub struct CompressorConfig {
+    pub threshold: f32,
+    pub knee: f32,
+    pub ratio: f32,
+    pub attack: f32,
+    pub release: f32,
+    pub normalize: bool,
+    pub target_level: f64,
+}
+
+#[wasm_bindgen]
+impl CompressorConfig {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> CompressorConfig {
+        CompressorConfig {
+            threshold: -24.0,
+            knee: 30.0,
+            ratio: 12.0,
+            attack: 0.003,
+            release: 0.25,
+            normalize: false,
+            target_level: 0.1,
+        }
+    }
+}
+
+impl Default for CompressorConfig {
+    fn default() -> Self {
+        CompressorConfig::new()
+    }
+}
+
+pub fn create_compressor(
+    context: &AudioContext,
+    config: &CompressorConfig,
+) -> Result<DynamicsCompressorNode, JsValue> {
+    let compressor = context.create_dynamics_compressor()?;
+    compressor.threshold().set_value(config.threshold);
+    compressor.knee().set_value(config.knee);
+    compressor.ratio().set_value(config.ratio);
+    compressor.attack().set_value(config.attack);
+    compressor.release().set_value(config.release);
+    Ok(compressor)
+}
+
+pub struct Normalizer {
+    samples: Vec<f32>,
+    target: f64,
+    gain: f64,
+}
+
+impl Normalizer {
+    pub fn new(target: f64) -> Normalizer {
+        Normalizer {
+            samples: Vec::new(),
+            target: target.clamp(0.01, 1.0),
+            gain: 1.0,
+        }
+    }
+
+    pub fn update(&mut self, analyser: &AnalyserNode) -> f64 {
+        let rms = rms(analyser, &mut self.samples);
+        // Leave silence alone instead of boosting the noise floor.
+        if rms < MIN_RMS {
+            return self.gain;
+        }
+
+        // The analyser sees the signal after our gain, so undo it to get the source level.
+        let wanted = (self.target * self.gain / rms).clamp(MIN_GAIN, MAX_GAIN);
+        self.gain += (wanted - self.gain) * ADAPT_RATE;
+        self.gain
+    }
+}
+
+// Reads the analyser's time-domain window into `samples` and returns its RMS level.
+pub fn rms(analyser: &AnalyserNode, samples: &mut Vec<f32>) -> f64 {
+    let length = analyser.fft_size() as usize;
+    if samples.len() != length {
+        samples.resize(length, 0.0);
+    }
+    analyser.get_float_time_domain_data(samples);
+
+    (samples.iter().map(|&s| (s as f64).powi(2)).sum::<f64>() / length.max(1) as f64).sqrt()
+}
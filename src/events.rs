@@ -0,0 +1,73 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::closure::Closure;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PlayerEvent {
+    Play,
+    Pause,
+    Ended,
+    Error,
+    Buffering,
+    TrackLoaded,
+    Progress,
+    Metadata,
+    Onset,
+    WaveformReady,
+    Silence,
+    Signal,
+    Stalled,
+    Recovered,
+}
+
+#[derive(Clone, Default)]
+pub struct EventCallbacks {
+    callbacks: Rc<RefCell<HashMap<PlayerEvent, js_sys::Function>>>,
+}
+
+impl EventCallbacks {
+    pub fn set(&self, event: PlayerEvent, callback: js_sys::Function) {
+        self.callbacks.borrow_mut().insert(event, callback);
+    }
+
+    pub fn has(&self, event: PlayerEvent) -> bool {
+        self.callbacks.borrow().contains_key(&event)
+    }
+
+    fn get(&self, event: PlayerEvent) -> Option<js_sys::Function> {
+        self.callbacks.borrow().get(&event).cloned()
+    }
+
+    pub fn emit(&self, event: PlayerEvent) {
+        if let Some(callback) = self.get(event) {
+            let _ = callback.call0(&JsValue::NULL);
+        }
+    }
+
+    pub fn emit1(&self, event: PlayerEvent, arg: &JsValue) {
+        if let Some(callback) = self.get(event) {
+            let _ = callback.call1(&JsValue::NULL, arg);
+        }
+    }
+
+    pub fn emit2(&self, event: PlayerEvent, first: &JsValue, second: &JsValue) {
+        if let Some(callback) = self.get(event) {
+            let _ = callback.call2(&JsValue::NULL, first, second);
+        }
+    }
+
+    pub fn error(&self, error: &JsValue) {
+        web_sys::console::error_1(error);
+        self.emit1(PlayerEvent::Error, error);
+    }
+
+    pub fn listener<F>(&self, handler: F) -> Closure<dyn FnMut()>
+    where
+        F: Fn(&EventCallbacks) + 'static,
+    {
+        let events = self.clone();
+        Closure::wrap(Box::new(move || handler(&events)) as Box<dyn FnMut()>)
+    }
+}
@@ -0,0 +1,65 @@
+use wasm_bindgen::prelude::*;
+
+const ROLLOFF_FRACTION: f64 = 0.85;
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Default)]
+pub struct SpectralFeatures {
+    pub centroid: f64,
+    pub rolloff: f64,
+    pub flatness: f64,
+    pub flux: f64,
+    pub brightness: f64,
+}
+
+impl SpectralFeatures {
+    pub fn compute(spectrum: &[u8], nyquist: f64, flux: f64) -> SpectralFeatures {
+        if spectrum.is_empty() {
+            return SpectralFeatures::default();
+        }
+
+        let bin_width = nyquist / spectrum.len() as f64;
+        let total: f64 = spectrum.iter().map(|&v| v as f64).sum();
+
+        let rolloff_bin = if total > 0.0 {
+            let target = total * ROLLOFF_FRACTION;
+            let mut cumulative = 0.0;
+            spectrum
+                .iter()
+                .position(|&v| {
+                    cumulative += v as f64;
+                    cumulative >= target
+                })
+                .unwrap_or(spectrum.len() - 1)
+        } else {
+            0
+        };
+
+        let log_sum: f64 = spectrum.iter().map(|&v| (v as f64 + 1.0).ln()).sum();
+        let count = spectrum.len() as f64;
+        let flatness = (log_sum / count).exp() / (total / count + 1.0);
+
+        let brightness = brightness(spectrum);
+        SpectralFeatures {
+            centroid: brightness * nyquist,
+            rolloff: (rolloff_bin as f64 + 0.5) * bin_width,
+            flatness,
+            flux,
+            brightness,
+        }
+    }
+}
+
+pub fn brightness(spectrum: &[u8]) -> f64 {
+    let total: f64 = spectrum.iter().map(|&v| v as f64).sum();
+    if total == 0.0 {
+        return 0.0;
+    }
+
+    let weighted: f64 = spectrum
+        .iter()
+        .enumerate()
+        .map(|(bin, &v)| (bin as f64 + 0.5) * v as f64)
+        .sum();
+    weighted / total / spectrum.len() as f64
+}
@@ -0,0 +1,59 @@
+pub type Mat4 = [f32; 16];
+
+pub fn identity() -> Mat4 {
+    let mut m = [0.0; 16];
+    m[0] = 1.0;
+    m[5] = 1.0;
+    m[10] = 1.0;
+    m[15] = 1.0;
+    m
+}
+
+pub fn perspective(fov_y: f32, aspect: f32, near: f32, far: f32) -> Mat4 {
+    let f = 1.0 / (fov_y / 2.0).tan();
+    let mut m = [0.0; 16];
+    m[0] = f / aspect;
+    m[5] = f;
+    m[10] = (far + near) / (near - far);
+    m[11] = -1.0;
+    m[14] = 2.0 * far * near / (near - far);
+    m
+}
+
+pub fn translation(x: f32, y: f32, z: f32) -> Mat4 {
+    let mut m = identity();
+    m[12] = x;
+    m[13] = y;
+    m[14] = z;
+    m
+}
+
+pub fn rotation_x(angle: f32) -> Mat4 {
+    let (s, c) = angle.sin_cos();
+    let mut m = identity();
+    m[5] = c;
+    m[6] = s;
+    m[9] = -s;
+    m[10] = c;
+    m
+}
+
+pub fn rotation_y(angle: f32) -> Mat4 {
+    let (s, c) = angle.sin_cos();
+    let mut m = identity();
+    m[0] = c;
+    m[2] = -s;
+    m[8] = s;
+    m[10] = c;
+    m
+}
+
+pub fn multiply(a: &Mat4, b: &Mat4) -> Mat4 {
+    let mut m = [0.0; 16];
+    for column in 0..4 {
+        for row in 0..4 {
+            m[column * 4 + row] = (0..4).map(|k| a[k * 4 + row] * b[column * 4 + k]).sum();
+        }
+    }
+    m
+}
@@ -0,0 +1,88 @@
+use std::f32::consts::PI;
+
+pub struct Sample {
+    pub row: usize,
+    pub band: f32,
+    pub weight: f32,
+}
+
+pub struct Mesh {
+    pub positions: Vec<f32>,
+    pub normals: Vec<f32>,
+    pub samples: Vec<Sample>,
+    pub indices: Vec<u16>,
+    pub rows: usize,
+}
+
+impl Mesh {
+    pub fn sphere(rings: usize, segments: usize) -> Mesh {
+        let mut mesh = Mesh::empty(1);
+        for ring in 0..=rings {
+            let theta = ring as f32 / rings as f32 * PI;
+            for segment in 0..segments {
+                let phi = segment as f32 / segments as f32 * PI * 2.0;
+                let normal = [theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin()];
+                mesh.positions.extend_from_slice(&normal);
+                mesh.normals.extend_from_slice(&normal);
+                mesh.samples.push(Sample {
+                    row: 0,
+                    band: 1.0 - (2.0 * segment as f32 / segments as f32 - 1.0).abs(),
+                    weight: theta.sin(),
+                });
+            }
+        }
+
+        let vertex = |ring: usize, segment: usize| (ring * segments + segment % segments) as u16;
+        for ring in 0..=rings {
+            for segment in 0..segments {
+                if ring > 0 && ring < rings {
+                    mesh.indices.extend([vertex(ring, segment), vertex(ring, segment + 1)]);
+                }
+                if ring < rings {
+                    mesh.indices.extend([vertex(ring, segment), vertex(ring + 1, segment)]);
+                }
+            }
+        }
+        mesh
+    }
+
+    pub fn terrain(rows: usize, columns: usize) -> Mesh {
+        let mut mesh = Mesh::empty(rows);
+        for row in 0..rows {
+            let z = 1.0 - 2.0 * row as f32 / (rows - 1) as f32;
+            for column in 0..columns {
+                let band = column as f32 / (columns - 1) as f32;
+                mesh.positions.extend_from_slice(&[band * 2.0 - 1.0, 0.0, z]);
+                mesh.normals.extend_from_slice(&[0.0, 1.0, 0.0]);
+                mesh.samples.push(Sample {
+                    row,
+                    band,
+                    weight: 1.0,
+                });
+            }
+        }
+
+        let vertex = |row: usize, column: usize| (row * columns + column) as u16;
+        for row in 0..rows {
+            for column in 0..columns {
+                if column + 1 < columns {
+                    mesh.indices.extend([vertex(row, column), vertex(row, column + 1)]);
+                }
+                if row + 1 < rows {
+                    mesh.indices.extend([vertex(row, column), vertex(row + 1, column)]);
+                }
+            }
+        }
+        mesh
+    }
+
+    fn empty(rows: usize) -> Mesh {
+        Mesh {
+            positions: Vec::new(),
+            normals: Vec::new(),
+            samples: Vec::new(),
+            indices: Vec::new(),
+            rows,
+        }
+    }
+}
@@ -0,0 +1,345 @@
+mod math;
+mod mesh;
+mod program;
+
+use crate::background::Background;
+use crate::document;
+use mesh::Mesh;
+use program::Program;
+use std::collections::VecDeque;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{
+    CanvasRenderingContext2d, HtmlCanvasElement, WebGl2RenderingContext as Gl, WebGlBuffer,
+    WebGlVertexArrayObject, WebglLoseContext,
+};
+
+const BAND_COUNT: usize = 32;
+const BAND_FALLOFF: f32 = 0.9;
+const MAX_SEGMENTS: usize = 64;
+const MIN_SEGMENTS: usize = 24;
+const ROTATION_STEP: f32 = 0.01;
+const REDUCED_MOTION_ROTATION_SCALE: f32 = 0.25;
+const PULSE_DECAY: f32 = 0.9;
+const HUE_STEP: f32 = 0.5;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Shape {
+    Sphere,
+    Terrain,
+}
+
+struct Buffers {
+    vao: WebGlVertexArrayObject,
+    heights: WebGlBuffer,
+    index_count: i32,
+    owned: Vec<WebGlBuffer>,
+}
+
+pub struct GlStyle {
+    shape: Shape,
+    ctx: CanvasRenderingContext2d,
+    canvas: HtmlCanvasElement,
+    gl: Gl,
+    program: Program,
+    mesh: Mesh,
+    buffers: Buffers,
+    segments: usize,
+    width: u32,
+    height: u32,
+    bands: Vec<f32>,
+    history: VecDeque<Vec<f32>>,
+    heights: Vec<f32>,
+    rotation: f32,
+    pulse: f32,
+    hue: f32,
+    key_hue: Option<f64>,
+    reduced_motion: bool,
+    background: Option<Background>,
+}
+
+impl GlStyle {
+    pub fn new(canvas: HtmlCanvasElement, shape: Shape) -> Result<GlStyle, JsValue> {
+        let ctx = canvas
+            .get_context("2d")?
+            .ok_or_else(|| JsValue::from_str("Failed to get 2D context"))?
+            .dyn_into::<CanvasRenderingContext2d>()?;
+
+        let gl_canvas: HtmlCanvasElement = document()?.create_element("canvas")?.dyn_into()?;
+        let gl = gl_canvas
+            .get_context("webgl2")?
+            .ok_or_else(|| JsValue::from_str("WebGL2 is not supported"))?
+            .dyn_into::<Gl>()?;
+        let program = Program::new(&gl)?;
+
+        let mesh = build_mesh(shape, MAX_SEGMENTS);
+        let buffers = upload(&gl, &program, &mesh)?;
+
+        Ok(GlStyle {
+            shape,
+            width: canvas.width(),
+            height: canvas.height(),
+            ctx,
+            canvas: gl_canvas,
+            gl,
+            program,
+            heights: vec![0.0; mesh.samples.len()],
+            history: VecDeque::from(vec![vec![0.0; BAND_COUNT]; mesh.rows]),
+            mesh,
+            buffers,
+            segments: MAX_SEGMENTS,
+            bands: vec![0.0; BAND_COUNT],
+            rotation: 0.0,
+            pulse: 0.0,
+            hue: 0.0,
+            key_hue: None,
+            reduced_motion: false,
+            background: None,
+        })
+    }
+
+    pub fn shape(&self) -> Shape {
+        self.shape
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+    }
+
+    pub fn set_context(&mut self, ctx: CanvasRenderingContext2d) {
+        self.ctx = ctx;
+    }
+
+    pub fn set_background(&mut self, background: Option<Background>) {
+        self.background = background;
+    }
+
+    pub fn set_reduced_motion(&mut self, enabled: bool) {
+        self.reduced_motion = enabled;
+    }
+
+    pub fn trigger_onset(&mut self) {
+        self.pulse = 1.0;
+    }
+
+    pub fn set_key_hue(&mut self, hue: Option<f64>) {
+        self.key_hue = hue;
+    }
+
+    pub fn set_quality(&mut self, quality: f64) {
+        let segments = (MAX_SEGMENTS as f64 * quality.clamp(0.0, 1.0)).round() as usize;
+        let segments = segments.clamp(MIN_SEGMENTS, MAX_SEGMENTS);
+        if segments == self.segments {
+            return;
+        }
+
+        let mesh = build_mesh(self.shape, segments);
+        let Ok(buffers) = upload(&self.gl, &self.program, &mesh) else {
+            return;
+        };
+        self.release_buffers();
+        self.heights = vec![0.0; mesh.samples.len()];
+        self.history = VecDeque::from(vec![vec![0.0; BAND_COUNT]; mesh.rows]);
+        self.mesh = mesh;
+        self.buffers = buffers;
+        self.segments = segments;
+    }
+
+    pub fn draw(&mut self, audio_data: &[u8]) {
+        match self.background {
+            Some(ref background) => background.paint(&self.ctx),
+            None => {
+                self.ctx.set_fill_style(&JsValue::from_str("rgba(0, 0, 0, 0.3)"));
+                self.ctx.fill_rect(0.0, 0.0, self.width as f64, self.height as f64);
+            }
+        }
+
+        self.update_bands(audio_data);
+        self.update_heights();
+        self.render();
+
+        let _ = self.ctx.draw_image_with_html_canvas_element_and_dw_and_dh(
+            &self.canvas,
+            0.0,
+            0.0,
+            self.width as f64,
+            self.height as f64,
+        );
+
+        let rotation_scale = if self.reduced_motion {
+            REDUCED_MOTION_ROTATION_SCALE
+        } else {
+            1.0
+        };
+        self.rotation = (self.rotation + ROTATION_STEP * rotation_scale) % std::f32::consts::TAU;
+        self.pulse *= PULSE_DECAY;
+        self.hue = match self.key_hue {
+            Some(hue) => hue as f32,
+            None => (self.hue + HUE_STEP * rotation_scale) % 360.0,
+        };
+    }
+
+    fn update_bands(&mut self, audio_data: &[u8]) {
+        let bins = audio_data.len();
+        for (index, band) in self.bands.iter_mut().enumerate() {
+            let value = if bins < 2 {
+                0.0
+            } else {
+                let edge = |i: usize| (bins as f32).powf(i as f32 / BAND_COUNT as f32) as usize;
+                let low = edge(index).min(bins - 1);
+                let high = edge(index + 1).clamp(low + 1, bins);
+                let sum: u32 = audio_data[low..high].iter().map(|&x| x as u32).sum();
+                sum as f32 / ((high - low) as f32 * 255.0)
+            };
+            *band = value.max(*band * BAND_FALLOFF);
+        }
+
+        if self.history.len() > 1 {
+            if let Some(mut row) = self.history.pop_back() {
+                row.copy_from_slice(&self.bands);
+                self.history.push_front(row);
+            }
+        } else if let Some(row) = self.history.front_mut() {
+            row.copy_from_slice(&self.bands);
+        }
+    }
+
+    fn update_heights(&mut self) {
+        for (height, sample) in self.heights.iter_mut().zip(&self.mesh.samples) {
+            let row = &self.history[sample.row];
+            let position = sample.band * (BAND_COUNT - 1) as f32;
+            let index = position as usize;
+            let next = (index + 1).min(BAND_COUNT - 1);
+            let fraction = position - index as f32;
+            *height = sample.weight * (row[index] * (1.0 - fraction) + row[next] * fraction);
+        }
+    }
+
+    fn render(&self) {
+        let gl = &self.gl;
+        let target = self.ctx.canvas();
+        let (width, height) = target
+            .map(|canvas| (canvas.width(), canvas.height()))
+            .unwrap_or((self.width, self.height));
+        if self.canvas.width() != width || self.canvas.height() != height {
+            self.canvas.set_width(width);
+            self.canvas.set_height(height);
+        }
+
+        gl.viewport(0, 0, width as i32, height as i32);
+        gl.clear_color(0.0, 0.0, 0.0, 0.0);
+        gl.clear(Gl::COLOR_BUFFER_BIT);
+        gl.enable(Gl::BLEND);
+        gl.blend_func(Gl::ONE, Gl::ONE_MINUS_SRC_ALPHA);
+
+        gl.use_program(Some(&self.program.program));
+        gl.bind_vertex_array(Some(&self.buffers.vao));
+        gl.bind_buffer(Gl::ARRAY_BUFFER, Some(&self.buffers.heights));
+        let heights = js_sys::Float32Array::from(self.heights.as_slice());
+        gl.buffer_sub_data_with_i32_and_array_buffer_view(Gl::ARRAY_BUFFER, 0, &heights);
+
+        let aspect = width.max(1) as f32 / height.max(1) as f32;
+        let projection = math::perspective(std::f32::consts::FRAC_PI_4, aspect, 0.1, 100.0);
+        let (view, amplitude) = match self.shape {
+            Shape::Sphere => (
+                math::multiply(
+                    &math::translation(0.0, 0.0, -3.2),
+                    &math::multiply(&math::rotation_x(0.3), &math::rotation_y(self.rotation)),
+                ),
+                0.35,
+            ),
+            Shape::Terrain => (
+                math::multiply(
+                    &math::translation(0.0, -0.3, -2.6),
+                    &math::multiply(&math::rotation_x(0.5), &math::rotation_y(self.rotation.sin() * 0.2)),
+                ),
+                0.6,
+            ),
+        };
+        let matrix = math::multiply(&projection, &view);
+
+        gl.uniform_matrix4fv_with_f32_array(self.program.matrix.as_ref(), false, &matrix);
+        gl.uniform1f(self.program.amplitude.as_ref(), amplitude * (1.0 + 0.5 * self.pulse));
+        gl.uniform1f(self.program.hue.as_ref(), self.hue / 360.0);
+        gl.draw_elements_with_i32(Gl::LINES, self.buffers.index_count, Gl::UNSIGNED_SHORT, 0);
+        gl.bind_vertex_array(None);
+    }
+
+    fn release_buffers(&self) {
+        self.gl.delete_vertex_array(Some(&self.buffers.vao));
+        for buffer in &self.buffers.owned {
+            self.gl.delete_buffer(Some(buffer));
+        }
+    }
+}
+
+impl Drop for GlStyle {
+    fn drop(&mut self) {
+        self.release_buffers();
+        self.gl.delete_program(Some(&self.program.program));
+        // Browsers cap live WebGL contexts, so hand this one back instead of waiting for GC.
+        if let Ok(Some(extension)) = self.gl.get_extension("WEBGL_lose_context") {
+            extension.unchecked_into::<WebglLoseContext>().lose_context();
+        }
+    }
+}
+
+fn build_mesh(shape: Shape, segments: usize) -> Mesh {
+    match shape {
+        Shape::Sphere => Mesh::sphere(segments / 2, segments),
+        Shape::Terrain => Mesh::terrain(segments * 3 / 4, segments),
+    }
+}
+
+fn upload(gl: &Gl, program: &Program, mesh: &Mesh) -> Result<Buffers, JsValue> {
+    let vao = gl
+        .create_vertex_array()
+        .ok_or_else(|| JsValue::from_str("Failed to create vertex array"))?;
+    gl.bind_vertex_array(Some(&vao));
+
+    let buffer = |target: u32, data: &js_sys::Object, usage: u32| -> Result<WebGlBuffer, JsValue> {
+        let buffer = gl
+            .create_buffer()
+            .ok_or_else(|| JsValue::from_str("Failed to create WebGL buffer"))?;
+        gl.bind_buffer(target, Some(&buffer));
+        gl.buffer_data_with_array_buffer_view(target, data, usage);
+        Ok(buffer)
+    };
+    let attribute = |location: u32, size: i32| {
+        gl.enable_vertex_attrib_array(location);
+        gl.vertex_attrib_pointer_with_i32(location, size, Gl::FLOAT, false, 0, 0);
+    };
+
+    let positions = buffer(
+        Gl::ARRAY_BUFFER,
+        &js_sys::Float32Array::from(mesh.positions.as_slice()),
+        Gl::STATIC_DRAW,
+    )?;
+    attribute(program.position, 3);
+    let normals = buffer(
+        Gl::ARRAY_BUFFER,
+        &js_sys::Float32Array::from(mesh.normals.as_slice()),
+        Gl::STATIC_DRAW,
+    )?;
+    attribute(program.normal, 3);
+    let heights = buffer(
+        Gl::ARRAY_BUFFER,
+        &js_sys::Float32Array::new_with_length(mesh.samples.len() as u32),
+        Gl::DYNAMIC_DRAW,
+    )?;
+    attribute(program.height, 1);
+    let indices = buffer(
+        Gl::ELEMENT_ARRAY_BUFFER,
+        &js_sys::Uint16Array::from(mesh.indices.as_slice()),
+        Gl::STATIC_DRAW,
+    )?;
+
+    gl.bind_vertex_array(None);
+    Ok(Buffers {
+        vao,
+        heights: heights.clone(),
+        index_count: mesh.indices.len() as i32,
+        owned: vec![positions, normals, heights, indices],
+    })
+}
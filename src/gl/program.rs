@@ -0,0 +1,105 @@
+use wasm_bindgen::prelude::*;
+use web_sys::{WebGl2RenderingContext as Gl, WebGlProgram, WebGlShader, WebGlUniformLocation};
+
+const VERTEX_SOURCE: &str = r#"#version 300 es
+in vec3 a_position;
+in vec3 a_normal;
+in float a_height;
+uniform mat4 u_matrix;
+uniform float u_amplitude;
+out float v_height;
+
+void main() {
+  v_height = a_height;
+  gl_Position = u_matrix * vec4(a_position + a_normal * a_height * u_amplitude, 1.0);
+}
+"#;
+
+const FRAGMENT_SOURCE: &str = r#"#version 300 es
+precision mediump float;
+in float v_height;
+uniform float u_hue;
+out vec4 color;
+
+vec3 hsl(float h, float s, float l) {
+  vec3 k = mod(vec3(0.0, 8.0, 4.0) + h * 12.0, 12.0);
+  float a = s * min(l, 1.0 - l);
+  return l - a * clamp(min(k - 3.0, 9.0 - k), -1.0, 1.0);
+}
+
+void main() {
+  float alpha = 0.5 + v_height * 0.5;
+  color = vec4(hsl(fract(u_hue + v_height * 0.35), 0.9, 0.45 + v_height * 0.25) * alpha, alpha);
+}
+"#;
+
+pub struct Program {
+    pub program: WebGlProgram,
+    pub position: u32,
+    pub normal: u32,
+    pub height: u32,
+    pub matrix: Option<WebGlUniformLocation>,
+    pub amplitude: Option<WebGlUniformLocation>,
+    pub hue: Option<WebGlUniformLocation>,
+}
+
+impl Program {
+    pub fn new(gl: &Gl) -> Result<Program, JsValue> {
+        let vertex = compile(gl, Gl::VERTEX_SHADER, VERTEX_SOURCE)?;
+        let fragment = compile(gl, Gl::FRAGMENT_SHADER, FRAGMENT_SOURCE)?;
+
+        let program = gl
+            .create_program()
+            .ok_or_else(|| JsValue::from_str("Failed to create WebGL program"))?;
+        gl.attach_shader(&program, &vertex);
+        gl.attach_shader(&program, &fragment);
+        gl.link_program(&program);
+        gl.delete_shader(Some(&vertex));
+        gl.delete_shader(Some(&fragment));
+
+        if !gl
+            .get_program_parameter(&program, Gl::LINK_STATUS)
+            .as_bool()
+            .unwrap_or(false)
+        {
+            let log = gl.get_program_info_log(&program).unwrap_or_default();
+            gl.delete_program(Some(&program));
+            return Err(JsValue::from_str(&format!("Failed to link WebGL program: {}", log)));
+        }
+
+        let attribute = |name: &str| -> Result<u32, JsValue> {
+            u32::try_from(gl.get_attrib_location(&program, name))
+                .map_err(|_| JsValue::from_str(&format!("Missing attribute {}", name)))
+        };
+
+        Ok(Program {
+            position: attribute("a_position")?,
+            normal: attribute("a_normal")?,
+            height: attribute("a_height")?,
+            matrix: gl.get_uniform_location(&program, "u_matrix"),
+            amplitude: gl.get_uniform_location(&program, "u_amplitude"),
+            hue: gl.get_uniform_location(&program, "u_hue"),
+            program,
+        })
+    }
+}
+
+fn compile(gl: &Gl, kind: u32, source: &str) -> Result<WebGlShader, JsValue> {
+    let shader = gl
+        .create_shader(kind)
+        .ok_or_else(|| JsValue::from_str("Failed to create WebGL shader"))?;
+    gl.shader_source(&shader, source);
+    gl.compile_shader(&shader);
+
+    if gl
+        .get_shader_parameter(&shader, Gl::COMPILE_STATUS)
+        .as_bool()
+        .unwrap_or(false)
+    {
+        Ok(shader)
+    } else {
+        let log = gl.get_shader_info_log(&shader).unwrap_or_default();
+        gl.delete_shader(Some(&shader));
+        Err(JsValue::from_str(&format!("Failed to compile WebGL shader: {}", log)))
+    }
+}
@@ -0,0 +1,377 @@
+use crate::events::EventCallbacks;
+use crate::request::RequestOptions;
+use crate::retry::Backoff;
+use crate::stream::{append, fetch_ok, is_closed, sleep, StreamOptions, LIVE_RETENTION_SECONDS};
+use crate::{log, STREAM_MIME_TYPE};
+use js_sys::Uint8Array;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{HtmlMediaElement, MediaSource, SourceBufferAppendMode, Window};
+
+pub const PLAYLIST_MIME_TYPE: &str = "application/vnd.apple.mpegurl";
+
+const LIVE_EDGE_SEGMENTS: usize = 3;
+
+pub struct Segment {
+    pub uri: String,
+    pub sequence: u64,
+}
+
+pub struct MediaPlaylist {
+    pub target_duration: f64,
+    pub init_segment: Option<String>,
+    pub segments: Vec<Segment>,
+    pub ended: bool,
+}
+
+pub enum Playlist {
+    Master(Vec<String>),
+    Media(MediaPlaylist),
+}
+
+pub fn is_playlist_url(url: &str) -> bool {
+    strip_query(url).to_ascii_lowercase().ends_with(".m3u8")
+}
+
+pub fn parse_playlist(text: &str, base_url: &str) -> Result<Playlist, JsValue> {
+    let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+
+    if lines.next() != Some("#EXTM3U") {
+        return Err(JsValue::from_str("Not an HLS playlist"));
+    }
+
+    let mut variants = Vec::new();
+    let mut expecting_variant = false;
+    let mut media_sequence = 0;
+    let mut playlist = MediaPlaylist {
+        target_duration: 0.0,
+        init_segment: None,
+        segments: Vec::new(),
+        ended: false,
+    };
+
+    for line in lines {
+        if let Some(value) = line.strip_prefix("#EXT-X-TARGETDURATION:") {
+            playlist.target_duration = value.parse().unwrap_or(0.0);
+        } else if let Some(value) = line.strip_prefix("#EXT-X-MEDIA-SEQUENCE:") {
+            media_sequence = value.parse().unwrap_or(0);
+        } else if let Some(value) = line.strip_prefix("#EXT-X-MAP:") {
+            if let Some(uri) = attribute(value, "URI") {
+                playlist.init_segment = Some(resolve(uri, base_url));
+            }
+        } else if let Some(value) = line.strip_prefix("#EXT-X-KEY:") {
+            if attribute(value, "METHOD") != Some("NONE") {
+                return Err(JsValue::from_str("Encrypted HLS segments are not supported"));
+            }
+        } else if line.starts_with("#EXT-X-STREAM-INF:") {
+            expecting_variant = true;
+        } else if line == "#EXT-X-ENDLIST" {
+            playlist.ended = true;
+        } else if !line.starts_with('#') {
+            let uri = resolve(line, base_url);
+            if expecting_variant {
+                variants.push(uri);
+                expecting_variant = false;
+            } else {
+                let sequence = media_sequence + playlist.segments.len() as u64;
+                playlist.segments.push(Segment { uri, sequence });
+            }
+        }
+    }
+
+    if !variants.is_empty() {
+        Ok(Playlist::Master(variants))
+    } else {
+        Ok(Playlist::Media(playlist))
+    }
+}
+
+pub async fn pump_playlist(
+    window: Window,
+    media_source: MediaSource,
+    audio_element: HtmlMediaElement,
+    url: String,
+    options: StreamOptions,
+    events: EventCallbacks,
+) -> Result<(), JsValue> {
+    let StreamOptions { retention, looping, request, retry, .. } = options;
+    let mut backoff = Backoff::new(retry);
+    let (playlist_url, mut playlist) =
+        load_media_playlist(&window, url, &request, &mut backoff, &events).await?;
+
+    let first_segment = playlist
+        .segments
+        .first()
+        .ok_or_else(|| JsValue::from_str("HLS playlist has no segments"))?;
+    let source_buffer = media_source.add_source_buffer(segment_mime_type(&first_segment.uri)?)?;
+    source_buffer.set_mode(SourceBufferAppendMode::Sequence);
+
+    if let Some(ref init_segment) = playlist.init_segment {
+        let mut bytes = backoff
+            .run(&window, &events, || fetch_bytes(&window, init_segment, &request))
+            .await?;
+        if !append(&source_buffer, &media_source, &audio_element, &mut bytes, None, &looping).await? {
+            return Ok(());
+        }
+    }
+
+    let retention = retention.or((!playlist.ended).then_some(LIVE_RETENTION_SECONDS));
+
+    let mut next_sequence = if playlist.ended {
+        first_segment.sequence
+    } else {
+        let live_edge = playlist.segments.len().saturating_sub(LIVE_EDGE_SEGMENTS);
+        playlist.segments[live_edge].sequence
+    };
+
+    loop {
+        let pending = next_sequence;
+        for segment in playlist.segments.iter().filter(|s| s.sequence >= pending) {
+            let mut bytes = backoff
+                .run(&window, &events, || fetch_bytes(&window, &segment.uri, &request))
+                .await?;
+
+            if is_closed(&media_source) {
+                log("MediaSource closed, stopping HLS playback");
+                return Ok(());
+            }
+
+            if !append(&source_buffer, &media_source, &audio_element, &mut bytes, retention, &looping).await? {
+                log("MediaSource closed, stopping HLS playback");
+                return Ok(());
+            }
+            next_sequence = segment.sequence + 1;
+        }
+
+        if playlist.ended {
+            log("Reached end of HLS playlist, ending stream");
+            media_source.end_of_stream()?;
+            return Ok(());
+        }
+
+        let refresh_ms = (playlist.target_duration.max(1.0) * 500.0) as i32;
+        sleep(&window, refresh_ms).await?;
+
+        if is_closed(&media_source) {
+            log("MediaSource closed, stopping HLS playback");
+            return Ok(());
+        }
+
+        let text = backoff
+            .run(&window, &events, || fetch_text(&window, &playlist_url, &request))
+            .await?;
+        playlist = match parse_playlist(&text, &playlist_url)? {
+            Playlist::Media(playlist) => playlist,
+            Playlist::Master(_) => {
+                return Err(JsValue::from_str("Media playlist turned into a master playlist"))
+            }
+        };
+    }
+}
+
+async fn load_media_playlist(
+    window: &Window,
+    url: String,
+    request: &RequestOptions,
+    backoff: &mut Backoff,
+    events: &EventCallbacks,
+) -> Result<(String, MediaPlaylist), JsValue> {
+    let text = backoff.run(window, events, || fetch_text(window, &url, request)).await?;
+    match parse_playlist(&text, &url)? {
+        Playlist::Media(playlist) => Ok((url, playlist)),
+        Playlist::Master(variants) => {
+            let variant_url = select_variant(variants)
+                .ok_or_else(|| JsValue::from_str("HLS master playlist has no variants"))?;
+            log(&format!("Selected HLS variant {}", variant_url));
+
+            let text = backoff
+                .run(window, events, || fetch_text(window, &variant_url, request))
+                .await?;
+            match parse_playlist(&text, &variant_url)? {
+                Playlist::Media(playlist) => Ok((variant_url, playlist)),
+                Playlist::Master(_) => Err(JsValue::from_str("Nested HLS master playlists are not supported")),
+            }
+        }
+    }
+}
+
+async fn fetch_text(window: &Window, url: &str, request: &RequestOptions) -> Result<String, JsValue> {
+    let response = fetch_ok(window, url, request).await?;
+    JsFuture::from(response.text()?)
+        .await?
+        .as_string()
+        .ok_or_else(|| JsValue::from_str("Playlist is not text"))
+}
+
+async fn fetch_bytes(window: &Window, url: &str, request: &RequestOptions) -> Result<Vec<u8>, JsValue> {
+    let response = fetch_ok(window, url, request).await?;
+    let array_buffer = JsFuture::from(response.array_buffer()?).await?;
+    Ok(Uint8Array::new(&array_buffer).to_vec())
+}
+
+// The first listed variant is the one the playlist author wants clients to start with.
+fn select_variant(variants: Vec<String>) -> Option<String> {
+    variants.into_iter().next()
+}
+
+fn segment_mime_type(uri: &str) -> Result<&'static str, JsValue> {
+    let path = strip_query(uri).to_ascii_lowercase();
+    let extension = path.rsplit('.').next().unwrap_or("");
+
+    match extension {
+        "aac" => Ok("audio/aac"),
+        "mp4" | "m4s" | "m4a" => Ok("audio/mp4; codecs=\"mp4a.40.2\""),
+        "ts" => Err(JsValue::from_str(
+            "MPEG-TS HLS segments cannot be appended to MediaSource; serve packed audio or fMP4",
+        )),
+        _ => Ok(STREAM_MIME_TYPE),
+    }
+}
+
+fn attribute<'a>(attributes: &'a str, name: &str) -> Option<&'a str> {
+    attributes.split(',').find_map(|pair| {
+        let (key, value) = pair.split_once('=')?;
+        if key.trim() == name {
+            Some(value.trim().trim_matches('"'))
+        } else {
+            None
+        }
+    })
+}
+
+// Resolved in Rust rather than through `URL` so playlist parsing also runs outside a browser.
+fn resolve(uri: &str, base_url: &str) -> String {
+    if has_scheme(uri) {
+        return uri.to_string();
+    }
+    let Some((scheme, rest)) = strip_query(base_url).split_once("://") else {
+        return uri.to_string();
+    };
+    if let Some(authority_and_path) = uri.strip_prefix("//") {
+        return format!("{}://{}", scheme, authority_and_path);
+    }
+
+    let (host, base_path) = rest.find('/').map_or((rest, "/"), |i| rest.split_at(i));
+    let (path, suffix) = uri.split_at(uri.find(['?', '#']).unwrap_or(uri.len()));
+    let path = if path.starts_with('/') {
+        path.to_string()
+    } else {
+        let directory = &base_path[..base_path.rfind('/').map_or(0, |i| i + 1)];
+        format!("{}{}", directory, path)
+    };
+    format!("{}://{}{}{}", scheme, host, remove_dot_segments(&path), suffix)
+}
+
+fn has_scheme(uri: &str) -> bool {
+    uri.split_once(':').is_some_and(|(scheme, _)| {
+        scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+    })
+}
+
+fn remove_dot_segments(path: &str) -> String {
+    let mut segments = Vec::new();
+    for part in path.split('/').skip(1) {
+        match part {
+            "." => {}
+            ".." => {
+                segments.pop();
+            }
+            _ => segments.push(part),
+        }
+    }
+    format!("/{}", segments.join("/"))
+}
+
+fn strip_query(url: &str) -> &str {
+    url.split(['?', '#']).next().unwrap_or(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = "https://cdn.example/live/audio/index.m3u8?token=abc";
+
+    fn parse(text: &str) -> Playlist {
+        match parse_playlist(text, BASE) {
+            Ok(playlist) => playlist,
+            Err(_) => panic!("playlist failed to parse"),
+        }
+    }
+
+    fn media(text: &str) -> MediaPlaylist {
+        match parse(text) {
+            Playlist::Media(playlist) => playlist,
+            Playlist::Master(_) => panic!("expected a media playlist"),
+        }
+    }
+
+    #[test]
+    fn media_playlist_numbers_segments_from_media_sequence() {
+        let playlist = media(
+            "#EXTM3U\n#EXT-X-TARGETDURATION:6\n#EXT-X-MEDIA-SEQUENCE:41\n\
+             #EXT-X-MAP:URI=\"init.mp4\"\n#EXTINF:6.0,\nseg41.m4s\n#EXTINF:6.0,\nseg42.m4s\n",
+        );
+        assert_eq!(playlist.target_duration, 6.0);
+        assert_eq!(
+            playlist.init_segment.as_deref(),
+            Some("https://cdn.example/live/audio/init.mp4")
+        );
+        let segments: Vec<_> = playlist
+            .segments
+            .iter()
+            .map(|segment| (segment.uri.as_str(), segment.sequence))
+            .collect();
+        assert_eq!(
+            segments,
+            [
+                ("https://cdn.example/live/audio/seg41.m4s", 41),
+                ("https://cdn.example/live/audio/seg42.m4s", 42),
+            ]
+        );
+        assert!(!playlist.ended);
+    }
+
+    #[test]
+    fn endlist_marks_playlist_ended() {
+        let playlist = media("#EXTM3U\n#EXTINF:4.0,\na.aac\n#EXT-X-ENDLIST\n");
+        assert!(playlist.ended);
+        assert_eq!(playlist.segments.len(), 1);
+    }
+
+    #[test]
+    fn master_playlist_lists_variants_in_order() {
+        let text = "#EXTM3U\n\
+            #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"aac\",URI=\"alt/index.m3u8\"\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=128000\nhigh/index.m3u8\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=64000\nlow/index.m3u8\n";
+        let Playlist::Master(variants) = parse(text) else {
+            panic!("expected a master playlist");
+        };
+        assert_eq!(
+            variants,
+            [
+                "https://cdn.example/live/audio/high/index.m3u8",
+                "https://cdn.example/live/audio/low/index.m3u8",
+            ]
+        );
+        assert_eq!(
+            select_variant(variants).as_deref(),
+            Some("https://cdn.example/live/audio/high/index.m3u8")
+        );
+    }
+
+    #[test]
+    fn resolves_relative_uris_against_the_playlist() {
+        assert_eq!(resolve("seg.aac", BASE), "https://cdn.example/live/audio/seg.aac");
+        assert_eq!(
+            resolve("./seg.aac?part=1", BASE),
+            "https://cdn.example/live/audio/seg.aac?part=1"
+        );
+        assert_eq!(resolve("../other/seg.aac", BASE), "https://cdn.example/live/other/seg.aac");
+        assert_eq!(resolve("/root/seg.aac", BASE), "https://cdn.example/root/seg.aac");
+        assert_eq!(resolve("//mirror.example/seg.aac", BASE), "https://mirror.example/seg.aac");
+        assert_eq!(resolve("http://other.example/seg.aac", BASE), "http://other.example/seg.aac");
+    }
+}
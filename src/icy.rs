@@ -0,0 +1,139 @@
+enum State {
+    Audio { remaining: usize },
+    Length,
+    Metadata { remaining: usize },
+}
+
+pub struct IcyDemuxer {
+    metaint: usize,
+    state: State,
+    metadata: Vec<u8>,
+}
+
+impl IcyDemuxer {
+    pub fn new(metaint: usize) -> IcyDemuxer {
+        IcyDemuxer {
+            metaint,
+            state: State::Audio { remaining: metaint },
+            metadata: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, chunk: &[u8], audio: &mut Vec<u8>) -> Option<String> {
+        let mut title = None;
+        let mut offset = 0;
+
+        while offset < chunk.len() {
+            match self.state {
+                State::Audio { remaining } => {
+                    let take = remaining.min(chunk.len() - offset);
+                    audio.extend_from_slice(&chunk[offset..offset + take]);
+                    offset += take;
+                    self.state = if take == remaining {
+                        State::Length
+                    } else {
+                        State::Audio { remaining: remaining - take }
+                    };
+                }
+                State::Length => {
+                    let length = chunk[offset] as usize * 16;
+                    offset += 1;
+                    self.metadata.clear();
+                    self.state = if length == 0 {
+                        State::Audio { remaining: self.metaint }
+                    } else {
+                        State::Metadata { remaining: length }
+                    };
+                }
+                State::Metadata { remaining } => {
+                    let take = remaining.min(chunk.len() - offset);
+                    self.metadata.extend_from_slice(&chunk[offset..offset + take]);
+                    offset += take;
+                    if take == remaining {
+                        if let Some(stream_title) = parse_stream_title(&self.metadata) {
+                            title = Some(stream_title);
+                        }
+                        self.state = State::Audio { remaining: self.metaint };
+                    } else {
+                        self.state = State::Metadata { remaining: remaining - take };
+                    }
+                }
+            }
+        }
+
+        title
+    }
+}
+
+fn parse_stream_title(metadata: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(metadata);
+    let start = text.find("StreamTitle='")? + "StreamTitle='".len();
+    let rest = &text[start..];
+    // Titles may contain "';" themselves, so only a quote that closes the field counts.
+    let end = rest
+        .match_indices("';")
+        .map(|(i, _)| i)
+        .find(|&i| ends_field(&rest[i + 2..]))?;
+    let title = rest[..end].trim();
+
+    if title.is_empty() {
+        None
+    } else {
+        Some(title.to_string())
+    }
+}
+
+fn ends_field(after: &str) -> bool {
+    let after = after.trim_end_matches('\0');
+    after.is_empty()
+        || after.split_once("='").is_some_and(|(key, _)| {
+            !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata_block(text: &str) -> Vec<u8> {
+        let blocks = text.len().div_ceil(16);
+        let mut block = vec![blocks as u8];
+        block.extend_from_slice(text.as_bytes());
+        block.resize(1 + blocks * 16, 0);
+        block
+    }
+
+    #[test]
+    fn interval_split_across_chunks() {
+        let mut stream = b"abcd".to_vec();
+        stream.extend(metadata_block("StreamTitle='Song';"));
+        stream.extend_from_slice(b"efgh");
+
+        let mut demuxer = IcyDemuxer::new(4);
+        let mut audio = Vec::new();
+        let titles: Vec<_> = stream
+            .chunks(3)
+            .filter_map(|chunk| demuxer.push(chunk, &mut audio))
+            .collect();
+        assert_eq!(audio, b"abcdefgh");
+        assert_eq!(titles, ["Song"]);
+    }
+
+    #[test]
+    fn zero_length_metadata_block() {
+        let mut demuxer = IcyDemuxer::new(4);
+        let mut audio = Vec::new();
+        assert_eq!(demuxer.push(b"abcd\0efgh\0ij", &mut audio), None);
+        assert_eq!(audio, b"abcdefghij");
+    }
+
+    #[test]
+    fn stream_title_containing_quote_semicolon() {
+        let mut stream = b"ab".to_vec();
+        stream.extend(metadata_block("StreamTitle='Rock';n'Roll';StreamUrl='';"));
+        let mut demuxer = IcyDemuxer::new(2);
+        let mut audio = Vec::new();
+        assert_eq!(demuxer.push(&stream, &mut audio).as_deref(), Some("Rock';n'Roll"));
+        assert_eq!(audio, b"ab");
+    }
+}
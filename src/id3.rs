@@ -0,0 +1,284 @@
+const HEADER_SIZE: usize = 10;
+const MAX_TAG_SIZE: usize = 16 * 1024 * 1024;
+const FRONT_COVER: u8 = 3;
+
+pub struct Picture {
+    pub mime_type: String,
+    pub data: Vec<u8>,
+}
+
+#[derive(Default)]
+pub struct Id3Tag {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub picture: Option<Picture>,
+}
+
+#[derive(Default)]
+pub struct Id3Reader {
+    buffer: Vec<u8>,
+    done: bool,
+}
+
+impl Id3Reader {
+    pub fn push(&mut self, chunk: &[u8]) -> Option<Id3Tag> {
+        if self.done {
+            return None;
+        }
+
+        self.buffer.extend_from_slice(chunk);
+        if self.buffer.len() < HEADER_SIZE {
+            return None;
+        }
+
+        let size = match tag_size(&self.buffer) {
+            Some(size) if size <= MAX_TAG_SIZE => size,
+            _ => {
+                self.finish();
+                return None;
+            }
+        };
+        if self.buffer.len() < size {
+            return None;
+        }
+
+        let tag = parse(&self.buffer[..size]);
+        self.finish();
+        tag
+    }
+
+    fn finish(&mut self) {
+        self.done = true;
+        self.buffer = Vec::new();
+    }
+}
+
+pub fn tag_size(header: &[u8]) -> Option<usize> {
+    if header.len() < HEADER_SIZE || &header[..3] != b"ID3" {
+        return None;
+    }
+
+    let footer = if header[3] == 4 && header[5] & 0x10 != 0 { HEADER_SIZE } else { 0 };
+    Some(HEADER_SIZE + syncsafe(&header[6..10]) + footer)
+}
+
+pub fn parse(data: &[u8]) -> Option<Id3Tag> {
+    let size = tag_size(data)?;
+    let version = data[3];
+    let flags = data[5];
+    if !(2..=4).contains(&version) || data.len() < size {
+        return None;
+    }
+
+    let mut body = data[HEADER_SIZE..size.min(data.len())].to_vec();
+    if version < 4 && flags & 0x80 != 0 {
+        body = remove_unsynchronisation(&body);
+    }
+
+    let mut offset = 0;
+    if version > 2 && flags & 0x40 != 0 && body.len() >= 4 {
+        let extended = if version == 4 {
+            syncsafe(&body[..4]) as u64
+        } else {
+            u32::from_be_bytes([body[0], body[1], body[2], body[3]]) as u64 + 4
+        };
+        if extended > body.len() as u64 {
+            return None;
+        }
+        offset = extended as usize;
+    }
+
+    let (id_length, header_length) = if version == 2 { (3, 6) } else { (4, 10) };
+    let mut tag = Id3Tag::default();
+    let mut picture_type = None;
+
+    while offset + header_length <= body.len() {
+        let header = &body[offset..offset + header_length];
+        if header[0] == 0 {
+            break;
+        }
+
+        let id = String::from_utf8_lossy(&header[..id_length]).into_owned();
+        let length = match version {
+            2 => (header[3] as usize) << 16 | (header[4] as usize) << 8 | header[5] as usize,
+            3 => u32::from_be_bytes([header[4], header[5], header[6], header[7]]) as usize,
+            _ => syncsafe(&header[4..8]),
+        };
+        let unsupported = match version {
+            3 => header[9] & 0xc0 != 0,
+            4 => header[9] & 0x0c != 0,
+            _ => false,
+        };
+
+        offset += header_length;
+        if length > body.len() - offset {
+            break;
+        }
+        let mut frame = body[offset..offset + length].to_vec();
+        offset += length;
+
+        if unsupported {
+            continue;
+        }
+        if version == 4 && header[9] & 0x02 != 0 {
+            frame = remove_unsynchronisation(&frame);
+        }
+
+        match id.as_str() {
+            "TIT2" | "TT2" => tag.title = decode_text(&frame),
+            "TPE1" | "TP1" => tag.artist = decode_text(&frame),
+            "TALB" | "TAL" => tag.album = decode_text(&frame),
+            "APIC" | "PIC" if picture_type != Some(FRONT_COVER) => {
+                if let Some((kind, picture)) = decode_picture(&frame, version) {
+                    picture_type = Some(kind);
+                    tag.picture = Some(picture);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Some(tag)
+}
+
+fn syncsafe(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .fold(0, |size, &byte| (size << 7) | (byte & 0x7f) as usize)
+}
+
+fn remove_unsynchronisation(data: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(data.len());
+    for (i, &byte) in data.iter().enumerate() {
+        if byte == 0 && i > 0 && data[i - 1] == 0xff {
+            continue;
+        }
+        output.push(byte);
+    }
+    output
+}
+
+fn decode_text(frame: &[u8]) -> Option<String> {
+    let (&encoding, text) = frame.split_first()?;
+    let text = decode_string(encoding, text);
+    let text = text.split('\0').next().unwrap_or_default().trim();
+    if text.is_empty() {
+        None
+    } else {
+        Some(text.to_string())
+    }
+}
+
+fn decode_picture(frame: &[u8], version: u8) -> Option<(u8, Picture)> {
+    let (&encoding, rest) = frame.split_first()?;
+
+    let (mime_type, rest) = if version == 2 {
+        if rest.len() < 3 {
+            return None;
+        }
+        let format = String::from_utf8_lossy(&rest[..3]).to_ascii_lowercase();
+        let mime_type = match format.as_str() {
+            "jpg" => "image/jpeg".to_string(),
+            other => format!("image/{}", other),
+        };
+        (mime_type, &rest[3..])
+    } else {
+        let end = rest.iter().position(|&byte| byte == 0)?;
+        let mime_type = String::from_utf8_lossy(&rest[..end]).into_owned();
+        let mime_type = if mime_type.contains('/') {
+            mime_type
+        } else {
+            format!("image/{}", mime_type.to_ascii_lowercase())
+        };
+        (mime_type, &rest[end + 1..])
+    };
+
+    let (&kind, rest) = rest.split_first()?;
+    let description_end = terminator_end(encoding, rest)?;
+    let data = rest[description_end..].to_vec();
+    if data.is_empty() {
+        return None;
+    }
+
+    Some((kind, Picture { mime_type, data }))
+}
+
+fn terminator_end(encoding: u8, data: &[u8]) -> Option<usize> {
+    if encoding == 1 || encoding == 2 {
+        data.chunks_exact(2)
+            .position(|pair| pair == [0, 0])
+            .map(|index| index * 2 + 2)
+    } else {
+        data.iter().position(|&byte| byte == 0).map(|index| index + 1)
+    }
+}
+
+fn decode_string(encoding: u8, data: &[u8]) -> String {
+    match encoding {
+        0 => data.iter().map(|&byte| byte as char).collect(),
+        1 | 2 => {
+            let (big_endian, data) = match data {
+                [0xfe, 0xff, rest @ ..] => (true, rest),
+                [0xff, 0xfe, rest @ ..] => (false, rest),
+                _ => (encoding == 2, data),
+            };
+            let units: Vec<u16> = data
+                .chunks_exact(2)
+                .map(|pair| {
+                    if big_endian {
+                        u16::from_be_bytes([pair[0], pair[1]])
+                    } else {
+                        u16::from_le_bytes([pair[0], pair[1]])
+                    }
+                })
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        _ => String::from_utf8_lossy(data).into_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(version: u8, flags: u8, body: &[u8]) -> Vec<u8> {
+        let size = body.len();
+        let mut data = vec![b'I', b'D', b'3', version, 0, flags];
+        data.extend((0..4).rev().map(|i| ((size >> (i * 7)) & 0x7f) as u8));
+        data.extend_from_slice(body);
+        data
+    }
+
+    fn frame(id: &[u8; 4], length: u32, content: &[u8]) -> Vec<u8> {
+        let mut data = id.to_vec();
+        data.extend_from_slice(&length.to_be_bytes());
+        data.extend_from_slice(&[0, 0]);
+        data.extend_from_slice(content);
+        data
+    }
+
+    #[test]
+    fn parses_title_frame() {
+        let data = tag(3, 0, &frame(b"TIT2", 6, b"\0Hello"));
+        let parsed = parse(&data).unwrap();
+        assert_eq!(parsed.title.as_deref(), Some("Hello"));
+    }
+
+    #[test]
+    fn ignores_frame_larger_than_tag() {
+        let mut body = frame(b"TIT2", 6, b"\0Hello");
+        body.extend(frame(b"TPE1", u32::MAX, b"\0Artist"));
+        let parsed = parse(&tag(3, 0, &body)).unwrap();
+        assert_eq!(parsed.title.as_deref(), Some("Hello"));
+        assert_eq!(parsed.artist, None);
+    }
+
+    #[test]
+    fn rejects_oversized_extended_header() {
+        let mut body = u32::MAX.to_be_bytes().to_vec();
+        body.extend(frame(b"TIT2", 6, b"\0Hello"));
+        assert!(parse(&tag(3, 0x40, &body)).is_none());
+    }
+}
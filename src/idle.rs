@@ -0,0 +1,17 @@
+use std::f64::consts::PI;
+
+const AMBIENT_LEVEL: f64 = 48.0;
+const DRIFT_RATE: f64 = 0.0006;
+const BREATH_PERIOD_MS: f64 = 8000.0;
+
+// A quiet, slowly rolling spectrum so styles keep moving while nothing plays.
+pub fn ambient_spectrum(data: &mut [u8], time_ms: f64) {
+    let length = data.len().max(1) as f64;
+    let breath = 0.75 + 0.25 * (time_ms * 2.0 * PI / BREATH_PERIOD_MS).sin();
+    for (bin, value) in data.iter_mut().enumerate() {
+        let x = bin as f64 / length;
+        let envelope = (1.0 - x).powi(2);
+        let wave = 0.5 + 0.5 * (time_ms * DRIFT_RATE + x * 2.0 * PI * 3.0).sin();
+        *value = (AMBIENT_LEVEL * breath * envelope * (0.6 + 0.4 * wave)) as u8;
+    }
+}
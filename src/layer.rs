@@ -0,0 +1,306 @@
+use crate::background::Background;
+use crate::features::SpectralFeatures;
+use crate::gl::{GlStyle, Shape};
+use crate::options::StyleOptions;
+use crate::rainbow_style::{Bg, ParticleConfig};
+use crate::ring_style::{Symmetry, Visualizer};
+use crate::waveform::Waveform;
+use crate::waveform_style::WaveformStyle;
+use std::rc::Rc;
+use crate::{document, StyleType};
+use serde::{Deserialize, Serialize};
+use tsify_next::Tsify;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, HtmlImageElement};
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize, Tsify)]
+#[serde(rename = "BlendName", rename_all = "lowercase")]
+pub enum BlendMode {
+    Normal,
+    Additive,
+    Screen,
+    Multiply,
+    Overlay,
+    Lighten,
+    Darken,
+    Difference,
+}
+
+impl BlendMode {
+    pub fn operation(self) -> &'static str {
+        match self {
+            BlendMode::Normal => "source-over",
+            BlendMode::Additive => "lighter",
+            BlendMode::Screen => "screen",
+            BlendMode::Multiply => "multiply",
+            BlendMode::Overlay => "overlay",
+            BlendMode::Lighten => "lighten",
+            BlendMode::Darken => "darken",
+            BlendMode::Difference => "difference",
+        }
+    }
+}
+
+pub enum Style {
+    Visualizer(Visualizer),
+    Bg(Bg),
+    Gl(GlStyle),
+    Waveform(WaveformStyle),
+}
+
+impl Style {
+    pub fn new(style_type: StyleType, canvas: HtmlCanvasElement) -> Result<Style, JsValue> {
+        Ok(match style_type {
+            StyleType::Visualizer => Style::Visualizer(Visualizer::new(canvas)?),
+            StyleType::Bg => Style::Bg(Bg::new(canvas)?),
+            StyleType::Sphere => Style::Gl(GlStyle::new(canvas, Shape::Sphere)?),
+            StyleType::Terrain => Style::Gl(GlStyle::new(canvas, Shape::Terrain)?),
+            StyleType::Waveform => Style::Waveform(WaveformStyle::new(canvas)?),
+        })
+    }
+
+    pub fn style_type(&self) -> StyleType {
+        match self {
+            Style::Visualizer(_) => StyleType::Visualizer,
+            Style::Bg(_) => StyleType::Bg,
+            Style::Gl(gl) => match gl.shape() {
+                Shape::Sphere => StyleType::Sphere,
+                Shape::Terrain => StyleType::Terrain,
+            },
+            Style::Waveform(_) => StyleType::Waveform,
+        }
+    }
+
+    pub fn draw(&mut self, audio_data: &[u8]) {
+        match self {
+            Style::Visualizer(visualizer) => visualizer.draw(audio_data),
+            Style::Bg(bg) => bg.draw(audio_data),
+            Style::Gl(gl) => gl.draw(audio_data),
+            Style::Waveform(waveform) => waveform.draw(audio_data),
+        }
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        match self {
+            Style::Visualizer(visualizer) => visualizer.resize(width, height),
+            Style::Bg(bg) => bg.resize(width, height),
+            Style::Gl(gl) => gl.resize(width, height),
+            Style::Waveform(waveform) => waveform.resize(width, height),
+        }
+    }
+
+    pub fn set_context(&mut self, ctx: CanvasRenderingContext2d) {
+        match self {
+            Style::Visualizer(visualizer) => visualizer.set_context(ctx),
+            Style::Bg(bg) => bg.set_context(ctx),
+            Style::Gl(gl) => gl.set_context(ctx),
+            Style::Waveform(waveform) => waveform.set_context(ctx),
+        }
+    }
+
+    pub fn set_background(&mut self, background: Option<Background>) {
+        match self {
+            Style::Visualizer(visualizer) => visualizer.set_background(background),
+            Style::Bg(bg) => bg.set_background(background),
+            Style::Gl(gl) => gl.set_background(background),
+            Style::Waveform(waveform) => waveform.set_background(background),
+        }
+    }
+
+    pub fn set_reduced_motion(&mut self, enabled: bool) {
+        match self {
+            Style::Visualizer(visualizer) => visualizer.set_reduced_motion(enabled),
+            Style::Bg(bg) => bg.set_reduced_motion(enabled),
+            Style::Gl(gl) => gl.set_reduced_motion(enabled),
+            Style::Waveform(waveform) => waveform.set_reduced_motion(enabled),
+        }
+    }
+
+    pub fn set_quality(&mut self, quality: f64) {
+        match self {
+            Style::Visualizer(visualizer) => visualizer.set_quality(quality),
+            Style::Bg(bg) => bg.set_quality(quality),
+            Style::Gl(gl) => gl.set_quality(quality),
+            Style::Waveform(_) => {}
+        }
+    }
+
+    pub fn trigger_onset(&mut self) {
+        match self {
+            Style::Visualizer(visualizer) => visualizer.trigger_onset(),
+            Style::Bg(bg) => bg.trigger_onset(),
+            Style::Gl(gl) => gl.trigger_onset(),
+            Style::Waveform(_) => {}
+        }
+    }
+
+    pub fn set_features(&mut self, features: &SpectralFeatures) {
+        if let Style::Bg(bg) = self {
+            bg.set_features(features);
+        }
+    }
+
+    pub fn set_key_hue(&mut self, hue: Option<f64>) {
+        match self {
+            Style::Visualizer(visualizer) => visualizer.set_key_hue(hue),
+            Style::Gl(gl) => gl.set_key_hue(hue),
+            Style::Waveform(waveform) => waveform.set_key_hue(hue),
+            Style::Bg(_) => {}
+        }
+    }
+
+    pub fn set_pan(&mut self, pan: f64) {
+        match self {
+            Style::Visualizer(visualizer) => visualizer.set_pan(pan),
+            Style::Bg(bg) => bg.set_pan(pan),
+            Style::Gl(_) | Style::Waveform(_) => {}
+        }
+    }
+
+    pub fn set_center_image(&mut self, image: Option<HtmlImageElement>) {
+        if let Style::Visualizer(visualizer) = self {
+            visualizer.set_center_image(image);
+        }
+    }
+
+    pub fn set_symmetry(&mut self, symmetry: Symmetry, rotation_speed: f64) -> bool {
+        match self {
+            Style::Visualizer(visualizer) => {
+                visualizer.set_symmetry(symmetry, rotation_speed);
+                true
+            }
+            Style::Bg(_) | Style::Gl(_) | Style::Waveform(_) => false,
+        }
+    }
+
+    pub fn options(&self) -> StyleOptions {
+        let symmetry = self.symmetry();
+        StyleOptions {
+            symmetry: symmetry.map(|(symmetry, _)| symmetry),
+            rotation_speed: symmetry.map_or(0.0, |(_, rotation_speed)| rotation_speed),
+            particles: self.particle_config(),
+        }
+    }
+
+    pub fn apply_options(&mut self, options: &StyleOptions) {
+        if let Some(symmetry) = options.symmetry {
+            self.set_symmetry(symmetry, options.rotation_speed);
+        }
+        if let Some(ref particles) = options.particles {
+            self.set_particle_config(particles);
+        }
+    }
+
+    pub fn symmetry(&self) -> Option<(Symmetry, f64)> {
+        match self {
+            Style::Visualizer(visualizer) => {
+                Some((visualizer.symmetry(), visualizer.rotation_speed()))
+            }
+            Style::Bg(_) | Style::Gl(_) | Style::Waveform(_) => None,
+        }
+    }
+
+    pub fn particle_config(&self) -> Option<ParticleConfig> {
+        match self {
+            Style::Bg(bg) => Some(bg.particle_config()),
+            Style::Visualizer(_) | Style::Gl(_) | Style::Waveform(_) => None,
+        }
+    }
+
+    pub fn set_waveform(&mut self, waveform: Option<Rc<Waveform>>) {
+        if let Style::Waveform(style) = self {
+            style.set_waveform(waveform);
+        }
+    }
+
+    pub fn set_progress(&mut self, progress: f64) {
+        if let Style::Waveform(style) = self {
+            style.set_progress(progress);
+        }
+    }
+
+    pub fn set_seed(&mut self, seed: u64) {
+        if let Style::Bg(bg) = self {
+            bg.set_seed(seed);
+        }
+    }
+
+    pub fn set_particle_config(&mut self, config: &ParticleConfig) -> bool {
+        match self {
+            Style::Bg(bg) => {
+                bg.set_particle_config(config);
+                true
+            }
+            Style::Visualizer(_) | Style::Gl(_) | Style::Waveform(_) => false,
+        }
+    }
+}
+
+pub struct Surface {
+    canvas: HtmlCanvasElement,
+    ctx: CanvasRenderingContext2d,
+}
+
+impl Surface {
+    pub fn new(width: u32, height: u32, pixel_ratio: f64) -> Result<Surface, JsValue> {
+        let canvas: HtmlCanvasElement = document()?.create_element("canvas")?.dyn_into()?;
+        let ctx = canvas
+            .get_context("2d")?
+            .ok_or_else(|| JsValue::from_str("Failed to get 2D context"))?
+            .dyn_into::<CanvasRenderingContext2d>()?;
+
+        let surface = Surface { canvas, ctx };
+        surface.resize(width, height, pixel_ratio)?;
+        Ok(surface)
+    }
+
+    pub fn canvas(&self) -> &HtmlCanvasElement {
+        &self.canvas
+    }
+
+    pub fn context(&self) -> &CanvasRenderingContext2d {
+        &self.ctx
+    }
+
+    pub fn resize(&self, width: u32, height: u32, pixel_ratio: f64) -> Result<(), JsValue> {
+        self.canvas.set_width(width);
+        self.canvas.set_height(height);
+        self.ctx
+            .set_transform(pixel_ratio, 0.0, 0.0, pixel_ratio, 0.0, 0.0)
+    }
+
+    pub fn copy_from(&self, source: &HtmlCanvasElement) -> Result<(), JsValue> {
+        self.ctx.save();
+        self.ctx.set_transform(1.0, 0.0, 0.0, 1.0, 0.0, 0.0)?;
+        let result = self.ctx.draw_image_with_html_canvas_element(source, 0.0, 0.0);
+        self.ctx.restore();
+        result
+    }
+
+    pub fn composite(
+        &self,
+        target: &CanvasRenderingContext2d,
+        alpha: f64,
+        operation: &str,
+    ) -> Result<(), JsValue> {
+        target.save();
+        target.set_transform(1.0, 0.0, 0.0, 1.0, 0.0, 0.0)?;
+        target.set_global_alpha(alpha);
+        target.set_global_composite_operation(operation)?;
+        let result = target.draw_image_with_html_canvas_element(&self.canvas, 0.0, 0.0);
+        target.restore();
+        result
+    }
+}
+
+pub const LAYER_FADE_ALPHA: f64 = 0.1;
+
+pub struct Layer {
+    pub id: usize,
+    pub style: Style,
+    pub surface: Surface,
+    pub blend: BlendMode,
+    pub opacity: f64,
+}
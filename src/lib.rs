@@ -1,34 +1,165 @@
 mod ring_style;
 mod rainbow_style;
+mod stream;
+mod icy;
+mod hls;
+mod media_session;
+mod output_device;
+mod recorder;
+mod snapshot;
+mod events;
+mod visibility;
+mod palette;
+mod id3;
+mod metadata;
+mod worklet;
+mod onset;
+mod pitch;
+mod features;
+mod camera;
+mod layer;
+mod gl;
+mod waveform;
+mod waveform_style;
+mod background;
+mod text;
+mod looping;
+mod preload;
+mod mixer;
+mod dynamics;
+mod preset;
+mod options;
+mod rng;
+mod offline;
+mod shared_output;
+mod weighting;
+mod silence;
+mod idle;
+mod request;
+mod retry;
+mod buffer_track;
 
-use ring_style::Visualizer;
-use rainbow_style::Bg;
-use std::cell::RefCell;
+use rainbow_style::ParticleConfig;
+use ring_style::Symmetry;
+use layer::{BlendMode, Layer, Style, Surface, LAYER_FADE_ALPHA};
+use background::Background;
+use text::{TextConfig, TextOverlay};
+use waveform::Waveform;
+use looping::{LoopMode, SharedLoop};
+use buffer_track::BufferTrack;
+use preload::{Preloaded, Standby};
+use mixer::Mixer;
+use shared_output::SharedOutput;
+use weighting::{Weighting, WeightingCurve};
+use silence::{SilenceChange, SilenceConfig, SilenceDetector};
+use dynamics::{CompressorConfig, Normalizer};
+use preset::{Preset, PRESET_VERSION};
+use options::{FetchOptions, InstanceOptions, LayerOptions, ProcessorOptions};
+use request::RequestOptions;
+use retry::RetryConfig;
+use stream::StreamOptions;
+use recorder::{Recorder, RecordingOptions};
+use events::{EventCallbacks, PlayerEvent};
+use metadata::SharedMetadata;
+use pitch::PitchNote;
+use features::SpectralFeatures;
+use camera::{Camera, CameraConfig, CameraTransform};
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
+use serde::{Deserialize, Serialize};
+use tsify_next::Tsify;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures::JsFuture;
+use std::future::Future;
 use web_sys::{
-    AudioContext, AudioBufferSourceNode, AnalyserNode, HtmlCanvasElement, CanvasRenderingContext2d,
+    AudioContext, AudioScheduledSourceNode, AnalyserNode, GainNode,
+    HtmlCanvasElement, CanvasRenderingContext2d, HtmlMediaElement, MediaSource, MediaStream,
+    MediaStreamAudioDestinationNode, Window,
 };
-use js_sys::Uint8Array;
 use wasm_bindgen::JsCast;
-use futures::channel::oneshot;
 use wasm_bindgen::closure::Closure;
 
+const STREAM_MIME_TYPE: &str = "audio/mpeg";
+const PROGRESS_INTERVAL_SECONDS: f64 = 0.25;
+const DEFAULT_FRAME_BUDGET_MS: f64 = 1000.0 / 60.0;
+const MIN_QUALITY: f64 = 0.25;
+const NATIVE_HLS_REQUEST_ERROR: &str =
+    "Request headers and credentials need MediaSource support for HLS playback";
+
 #[wasm_bindgen]
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize, Tsify)]
+#[serde(rename = "StyleName", rename_all = "lowercase")]
 pub enum StyleType {
     Visualizer,
     Bg,
+    Sphere,
+    Terrain,
+    Waveform,
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq)]
+pub enum PlaybackMode {
+    MediaSource,
+    DecodeAudioData,
+    Pcm,
+    MediaStream,
+    Native,
 }
 
 #[wasm_bindgen]
 pub struct SharedAudioProcessor {
     context: AudioContext,
     analyser: AnalyserNode,
-    source: Option<Rc<RefCell<AudioBufferSourceNode>>>,
+    mixer: Mixer,
+    compressor: Option<web_sys::DynamicsCompressorNode>,
+    normalizer: RefCell<Option<Normalizer>>,
+    pan: f32,
+    pan_visuals: bool,
+    buffer_track: Rc<RefCell<Option<BufferTrack>>>,
+    pcm_bus: Option<(usize, GainNode)>,
+    pcm_next_start_time: f64,
+    audio_element: Rc<RefCell<Option<HtmlMediaElement>>>,
+    sink_destination: Option<MediaStreamAudioDestinationNode>,
+    sink_element: Option<HtmlMediaElement>,
     is_playing: bool,
-    on_audio_end: Option<js_sys::Function>,
+    started_at: f64,
+    last_progress_at: Cell<f64>,
+    playback_mode: Option<PlaybackMode>,
+    events: EventCallbacks,
+    on_next_track: Option<js_sys::Function>,
+    on_previous_track: Option<js_sys::Function>,
+    media_session_handlers: Option<media_session::MediaSessionHandlers>,
+    recorder: Option<Recorder>,
+    resize_observer: Option<(web_sys::ResizeObserver, Closure<dyn FnMut()>)>,
+    fullscreen_listener: Option<Closure<dyn FnMut()>>,
+    reduced_motion: Option<bool>,
+    visibility: Option<visibility::PageVisibility>,
+    max_fps: Option<u32>,
+    last_frame_at: Cell<f64>,
+    adaptive_quality: bool,
+    quality: Cell<f64>,
+    frame_cost: Cell<f64>,
+    frequency_data: RefCell<Vec<u8>>,
+    shared_output: Option<SharedOutput>,
+    weighting: RefCell<WeightingCurve>,
+    silence: RefCell<Option<SilenceDetector>>,
+    idle_animation: bool,
+    retry: Option<RetryConfig>,
+    source_frequency_data: RefCell<Vec<u8>>,
+    buffer_retention: Option<f64>,
+    metadata: SharedMetadata,
+    show_album_art: bool,
+    worklet: Option<worklet::WorkletAnalyser>,
+    onset: RefCell<onset::OnsetDetector>,
+    pitch: RefCell<pitch::PitchDetector>,
+    color_by_pitch: bool,
+    features: Cell<SpectralFeatures>,
+    camera: RefCell<Option<Camera>>,
+    waveform: Option<Rc<Waveform>>,
+    looping: SharedLoop,
+    preloaded: Option<Preloaded>,
+    seed: Option<u64>,
     instances: Rc<RefCell<Vec<AudioVisualizerInstance>>>,
 }
 
@@ -43,296 +174,2690 @@ impl SharedAudioProcessor {
         analyser.set_fft_size(256);
         analyser.set_smoothing_time_constant(0.8);
 
-        Ok(SharedAudioProcessor {
+        let audio_element = Rc::new(RefCell::new(None));
+        let visibility =
+            visibility::PageVisibility::install(audio_element.clone(), context.clone()).ok();
+
+        let mixer = Mixer::new(&context, &analyser)?;
+        let pitch = pitch::PitchDetector::new(&context, mixer.mix())?;
+
+        let processor = SharedAudioProcessor {
             context,
             analyser,
-            source: None,
+            mixer,
+            compressor: None,
+            normalizer: RefCell::new(None),
+            pan: 0.0,
+            pan_visuals: false,
+            buffer_track: Rc::new(RefCell::new(None)),
+            pcm_bus: None,
+            pcm_next_start_time: 0.0,
+            audio_element,
+            sink_destination: None,
+            sink_element: None,
             is_playing: false,
-            on_audio_end: None,
+            started_at: 0.0,
+            last_progress_at: Cell::new(0.0),
+            playback_mode: None,
+            events: EventCallbacks::default(),
+            on_next_track: None,
+            on_previous_track: None,
+            media_session_handlers: None,
+            recorder: None,
+            resize_observer: None,
+            fullscreen_listener: None,
+            reduced_motion: None,
+            visibility,
+            max_fps: None,
+            last_frame_at: Cell::new(f64::NEG_INFINITY),
+            adaptive_quality: false,
+            quality: Cell::new(1.0),
+            frame_cost: Cell::new(0.0),
+            frequency_data: RefCell::new(Vec::new()),
+            shared_output: None,
+            weighting: RefCell::new(WeightingCurve::new()),
+            silence: RefCell::new(None),
+            idle_animation: false,
+            retry: Some(RetryConfig::new()),
+            source_frequency_data: RefCell::new(Vec::new()),
+            buffer_retention: None,
+            metadata: SharedMetadata::default(),
+            show_album_art: false,
+            worklet: None,
+            onset: RefCell::new(onset::OnsetDetector::new()),
+            pitch: RefCell::new(pitch),
+            color_by_pitch: false,
+            features: Cell::new(SpectralFeatures::default()),
+            camera: RefCell::new(None),
+            waveform: None,
+            looping: SharedLoop::default(),
+            preloaded: None,
+            seed: None,
             instances: Rc::new(RefCell::new(Vec::new())),
-        })
+        };
+        processor.connect_output()?;
+        Ok(processor)
+    }
+
+    #[wasm_bindgen]
+    pub fn with_options(options: ProcessorOptions) -> Result<SharedAudioProcessor, JsValue> {
+        let mut processor = SharedAudioProcessor::new()?;
+        processor.apply_settings(&options)?;
+        if let Some(retention) = options.buffer_retention {
+            processor.set_buffer_retention(retention)?;
+        }
+        if let Some(enabled) = options.pause_audio_when_hidden {
+            processor.set_pause_audio_when_hidden(enabled);
+        }
+        Ok(processor)
     }
 
     #[wasm_bindgen]
     pub fn add_instance(
         &mut self,
         canvas: HtmlCanvasElement,
-        style_type: StyleType,
+        options: InstanceOptions,
     ) -> Result<usize, JsValue> {
-        let instance = AudioVisualizerInstance::new(canvas, style_type)?;
-        self.instances.borrow_mut().push(instance);
-        Ok(self.instances.borrow().len() - 1)
+        if let Some(source_id) = options.source {
+            self.mixer
+                .attach_analyser(source_id, &self.context, &self.analyser)?;
+        }
+        let id = self.insert_instance(canvas, options.style, options.source)?;
+        self.with_instance(id, |instance| instance.apply_preset(&options))?;
+        Ok(id)
     }
 
     #[wasm_bindgen]
-    pub fn set_on_audio_end(&mut self, callback: js_sys::Function) {
-        self.on_audio_end = Some(callback);
+    pub fn resize(&self) -> Result<(), JsValue> {
+        for instance in self.instances.borrow_mut().iter_mut() {
+            instance.resize()?;
+        }
+        Ok(())
     }
 
     #[wasm_bindgen]
-    pub async fn process_audio_from_path(&mut self, path: &str) -> Result<(), JsValue> {
-        use web_sys::{MediaSource, Response, HtmlMediaElement};
+    pub fn set_reduced_motion(&mut self, enabled: Option<bool>) {
+        self.reduced_motion = enabled;
+        let enabled = self.reduced_motion_enabled();
+        for instance in self.instances.borrow_mut().iter_mut() {
+            instance.set_reduced_motion(enabled);
+        }
+    }
 
-        log("Starting streaming audio processing");
+    #[wasm_bindgen]
+    pub fn reduced_motion_enabled(&self) -> bool {
+        self.reduced_motion.unwrap_or_else(prefers_reduced_motion)
+    }
 
-        let window = web_sys::window().ok_or_else(|| JsValue::from_str("No window found"))?;
-        let document = window
-            .document()
-            .ok_or_else(|| JsValue::from_str("No document found"))?;
-        let audio_element: HtmlMediaElement = document.create_element("audio")?.dyn_into()?;
+    #[wasm_bindgen]
+    pub fn set_max_fps(&mut self, fps: u32) {
+        self.max_fps = if fps == 0 { None } else { Some(fps) };
+    }
 
-        let media_source = MediaSource::new()?;
-        let media_url = web_sys::Url::create_object_url_with_source(&media_source)?;
+    #[wasm_bindgen]
+    pub fn set_idle_animation(&mut self, enabled: bool) {
+        self.idle_animation = enabled;
+    }
 
-        audio_element.set_src(&media_url);
-        audio_element.set_cross_origin(Some("anonymous"));
+    #[wasm_bindgen]
+    pub fn set_buffer_retention(&mut self, seconds: Option<f64>) -> Result<(), JsValue> {
+        if seconds.is_some_and(|seconds| seconds.is_nan() || seconds < 0.0) {
+            return Err(JsValue::from_str("Buffer retention must be a non-negative number of seconds"));
+        }
+        self.buffer_retention = seconds;
+        Ok(())
+    }
 
-        let media_element_source = self.context.create_media_element_source(&audio_element)?;
-        media_element_source.connect_with_audio_node(&self.analyser)?;
-        self.analyser
-            .connect_with_audio_node(&self.context.destination())?;
+    #[wasm_bindgen]
+    pub async fn set_worklet_analysis(&mut self, enabled: bool) -> Result<(), JsValue> {
+        if let Some(worklet) = self.worklet.take() {
+            worklet.disconnect(&self.analyser);
+        }
+        if enabled {
+            self.worklet = Some(worklet::WorkletAnalyser::install(&self.context, &self.analyser).await?);
+            log("Using AudioWorklet analysis");
+        }
+        Ok(())
+    }
 
-        let media_source_clone = media_source.clone();
-        let window_clone = window.clone();
+    #[wasm_bindgen]
+    pub fn band_energies(&self) -> Vec<f32> {
+        self.worklet
+            .as_ref()
+            .map(|worklet| worklet.bands())
+            .unwrap_or_default()
+    }
 
-        let server_url = if !path.starts_with("http") {
-            format!("http://127.0.0.1:3000{}", path)
-        } else {
-            path.to_string()
-        };
+    #[wasm_bindgen]
+    pub fn analysis_time(&self) -> f64 {
+        match self.worklet {
+            Some(ref worklet) => worklet.time(),
+            None => self.context.current_time(),
+        }
+    }
 
-        let on_source_open = Closure::once(Box::new(move || {
-            wasm_bindgen_futures::spawn_local(async move {
-                match async move {
-                    log("MediaSource opened, creating SourceBuffer");
-                    let source_buffer = media_source_clone.add_source_buffer("audio/mpeg")?;
+    /// Byte spectrum from the AnalyserNode. This and the float and time-domain
+    /// getters all read the analyser, even while worklet analysis drives drawing,
+    /// so the three always describe the same signal.
+    #[wasm_bindgen]
+    pub fn get_frequency_data(&self) -> Vec<u8> {
+        let mut data = vec![0u8; self.analyser.frequency_bin_count() as usize];
+        self.analyser.get_byte_frequency_data(&mut data);
+        data
+    }
 
-                    let fetch_promise = window_clone.fetch_with_str(&server_url);
-                    let response: Response =
-                        JsFuture::from(fetch_promise).await?.dyn_into()?;
+    #[wasm_bindgen]
+    pub fn set_shared_output(
+        &mut self,
+        buffer: Option<js_sys::SharedArrayBuffer>,
+    ) -> Result<(), JsValue> {
+        let bins = self.analyser.frequency_bin_count() as usize;
+        self.shared_output = buffer
+            .map(|buffer| SharedOutput::new(&buffer, bins))
+            .transpose()?;
+        Ok(())
+    }
 
-                    if !response.ok() {
-                        return Err(JsValue::from_str("Failed to fetch audio file"));
-                    }
+    #[wasm_bindgen]
+    pub fn get_float_frequency_data(&self) -> Vec<f32> {
+        let mut data = vec![0.0f32; self.analyser.frequency_bin_count() as usize];
+        self.analyser.get_float_frequency_data(&mut data);
+        data
+    }
 
-                    let body = response
-                        .body()
-                        .ok_or_else(|| JsValue::from_str("No response body"))?;
-                    let reader = body
-                        .get_reader()
-                        .dyn_into::<web_sys::ReadableStreamDefaultReader>()?;
-
-                    loop {
-                        let chunk = JsFuture::from(reader.read()).await?;
-                        let obj = js_sys::Object::from(chunk);
-
-                        let done = js_sys::Reflect::get(&obj, &"done".into())?
-                            .as_bool()
-                            .unwrap_or(false);
-
-                        if done {
-                            log("All data has been read, ending stream");
-                            media_source_clone.end_of_stream()?;
-                            break;
-                        }
+    #[wasm_bindgen]
+    pub fn get_time_domain_data(&self) -> Vec<u8> {
+        let mut data = vec![0u8; self.analyser.fft_size() as usize];
+        self.analyser.get_byte_time_domain_data(&mut data);
+        data
+    }
 
-                        if let Ok(value) = js_sys::Reflect::get(&obj, &"value".into()) {
-                            let array = js_sys::Uint8Array::new(&value);
+    #[wasm_bindgen]
+    pub fn set_adaptive_quality(&mut self, enabled: bool) {
+        self.adaptive_quality = enabled;
+        if !enabled {
+            self.apply_quality(1.0);
+        }
+    }
 
-                            source_buffer.append_buffer_with_array_buffer(&array.buffer())?;
-                            wait_for_updateend(&source_buffer).await?;
-                            log("Successfully appended buffer");
-                        }
-                    }
-                    Ok(())
-                }
-                .await
-                {
-                    Ok(()) => (),
-                    Err(e) => {
-                        web_sys::console::error_1(&e);
-                    }
-                }
-            });
-        }));
-        media_source.set_onsourceopen(Some(on_source_open.as_ref().unchecked_ref()));
-        on_source_open.forget();
+    #[wasm_bindgen]
+    pub fn quality(&self) -> f64 {
+        self.quality.get()
+    }
 
-        let on_ended = {
-            let on_audio_end = self.on_audio_end.clone();
-            Closure::wrap(Box::new(move || {
-                log("Audio playback ended");
-                if let Some(ref callback) = on_audio_end {
-                    let this = JsValue::NULL;
-                    let _ = callback.call0(&this);
-                }
-            }) as Box<dyn FnMut()>)
-        };
-        audio_element.set_onended(Some(on_ended.as_ref().unchecked_ref()));
-        on_ended.forget();
+    #[wasm_bindgen]
+    pub fn set_pause_audio_when_hidden(&mut self, enabled: bool) {
+        if let Some(ref visibility) = self.visibility {
+            visibility.set_pause_audio(enabled);
+        }
+    }
 
-        let play_promise = audio_element.play()?;
-        JsFuture::from(play_promise).await?;
+    #[wasm_bindgen]
+    pub fn set_instance_style(
+        &self,
+        instance_id: usize,
+        style_type: StyleType,
+        transition_ms: f64,
+    ) -> Result<(), JsValue> {
+        self.with_instance(instance_id, |instance| {
+            instance.set_style(style_type, transition_ms)
+        })
+    }
 
-        self.is_playing = true;
+    #[wasm_bindgen]
+    pub fn add_layer(
+        &self,
+        instance_id: usize,
+        style_type: StyleType,
+        blend: BlendMode,
+        opacity: f64,
+    ) -> Result<usize, JsValue> {
+        self.with_instance(instance_id, |instance| {
+            instance.add_layer(style_type, blend, opacity)
+        })
+    }
 
-        audio_element.set_attribute("style", "display: none")?;
-        document
-            .body()
-            .ok_or_else(|| JsValue::from_str("No body found"))?
-            .append_child(&audio_element)?;
+    #[wasm_bindgen]
+    pub fn remove_layer(&self, instance_id: usize, layer_id: usize) -> Result<(), JsValue> {
+        self.with_instance(instance_id, |instance| instance.remove_layer(layer_id))
+    }
 
-        Ok(())
+    #[wasm_bindgen]
+    pub fn set_layer_blend(
+        &self,
+        instance_id: usize,
+        layer_id: usize,
+        blend: BlendMode,
+    ) -> Result<(), JsValue> {
+        self.with_instance(instance_id, |instance| {
+            instance.layer_mut(layer_id)?.blend = blend;
+            Ok(())
+        })
     }
 
     #[wasm_bindgen]
-    pub fn stop_audio(&mut self) -> Result<(), JsValue> {
-        self.is_playing = false;
+    pub fn set_layer_background(
+        &self,
+        instance_id: usize,
+        layer_id: usize,
+        background: Option<Background>,
+    ) -> Result<(), JsValue> {
+        self.with_instance(instance_id, |instance| {
+            instance.layer_mut(layer_id)?.style.set_background(background);
+            Ok(())
+        })
+    }
 
-        if let Some(window) = web_sys::window() {
-            if let Some(document) = window.document() {
-                let audio_elements = document.get_elements_by_tag_name("audio");
-                let length = audio_elements.length();
-                for i in 0..length {
-                    if let Some(audio) = audio_elements.item(i) {
-                        if let Some(parent) = audio.parent_node() {
-                            parent.remove_child(&audio)?;
-                        }
-                    }
-                }
-            }
+    #[wasm_bindgen]
+    pub fn set_background(
+        &self,
+        instance_id: usize,
+        background: Option<Background>,
+    ) -> Result<(), JsValue> {
+        self.with_instance(instance_id, |instance| {
+            instance.set_background(background);
+            Ok(())
+        })
+    }
+
+    #[wasm_bindgen]
+    pub fn set_layer_opacity(
+        &self,
+        instance_id: usize,
+        layer_id: usize,
+        opacity: f64,
+    ) -> Result<(), JsValue> {
+        self.with_instance(instance_id, |instance| {
+            instance.layer_mut(layer_id)?.opacity = opacity.clamp(0.0, 1.0);
+            Ok(())
+        })
+    }
+
+    #[wasm_bindgen]
+    pub fn enable_compressor(&mut self, config: &CompressorConfig) -> Result<(), JsValue> {
+        self.disconnect_output()?;
+        self.compressor = Some(dynamics::create_compressor(&self.context, config)?);
+        self.connect_output()?;
+
+        *self.normalizer.borrow_mut() = config
+            .normalize
+            .then(|| Normalizer::new(config.target_level));
+        if !config.normalize {
+            self.mixer.set_level(1.0);
         }
+        Ok(())
+    }
 
-        self.clear_all();
+    #[wasm_bindgen]
+    pub fn disable_compressor(&mut self) -> Result<(), JsValue> {
+        self.disconnect_output()?;
+        self.compressor = None;
+        self.connect_output()?;
+
+        *self.normalizer.borrow_mut() = None;
+        self.mixer.set_level(1.0);
         Ok(())
     }
 
     #[wasm_bindgen]
-    pub fn draw(&self) {
-        if !self.is_playing {
-            return;
-        }
+    pub fn set_muted(&self, muted: bool) {
+        self.mixer.set_muted(muted);
+    }
+
+    #[wasm_bindgen]
+    pub fn is_muted(&self) -> bool {
+        self.mixer.is_muted()
+    }
 
-        let buffer_length = self.analyser.frequency_bin_count();
-        let mut data_array = vec![0u8; buffer_length as usize];
-        self.analyser.get_byte_frequency_data(&mut data_array);
+    #[wasm_bindgen]
+    pub fn set_pan(&mut self, pan: f32) {
+        self.pan = if pan.is_nan() { 0.0 } else { pan.clamp(-1.0, 1.0) };
+        self.mixer.set_pan(self.pan);
+        self.apply_visual_pan();
+    }
 
-        let mut instances = self.instances.borrow_mut();
-        for instance in instances.iter_mut() {
-            instance.draw(&data_array);
+    #[wasm_bindgen]
+    pub fn pan(&self) -> f32 {
+        self.pan
+    }
+
+    #[wasm_bindgen]
+    pub fn set_pan_visuals(&mut self, enabled: bool) {
+        self.pan_visuals = enabled;
+        self.apply_visual_pan();
+    }
+
+    #[wasm_bindgen]
+    pub fn set_camera_effect(&mut self, config: Option<CameraConfig>) {
+        let mut camera = config.map(Camera::new);
+        if let (Some(camera), Some(seed)) = (camera.as_mut(), self.seed) {
+            camera.set_seed(seed);
         }
+        *self.camera.borrow_mut() = camera;
     }
 
     #[wasm_bindgen]
-    pub fn clear_all(&self) {
-        let mut instances = self.instances.borrow_mut();
-        for instance in instances.iter_mut() {
-            instance.clear_canvas();
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+        if let Some(ref mut camera) = *self.camera.borrow_mut() {
+            camera.set_seed(seed);
+        }
+        for instance in self.instances.borrow_mut().iter_mut() {
+            instance.set_seed(seed);
         }
     }
-}
 
-struct AudioVisualizerInstance {
-    visualizer: Option<Visualizer>,
-    bg: Option<Bg>,
-    style_type: StyleType,
-    canvas: HtmlCanvasElement,
-    ctx: CanvasRenderingContext2d,
-}
+    #[wasm_bindgen]
+    pub fn set_symmetry(
+        &self,
+        instance_id: usize,
+        symmetry: Symmetry,
+        rotation_speed: f64,
+    ) -> Result<(), JsValue> {
+        self.with_instance(instance_id, |instance| {
+            if instance.set_symmetry(symmetry, rotation_speed) {
+                Ok(())
+            } else {
+                Err(JsValue::from_str("Symmetry only applies to Visualizer instances"))
+            }
+        })
+    }
 
-impl AudioVisualizerInstance {
-    fn new(canvas: HtmlCanvasElement, style_type: StyleType) -> Result<Self, JsValue> {
-        let ctx = canvas
-            .get_context("2d")?
-            .ok_or_else(|| JsValue::from_str("Failed to get 2D context"))?
-            .dyn_into::<CanvasRenderingContext2d>()?;
+    #[wasm_bindgen]
+    pub fn set_particle_config(
+        &self,
+        instance_id: usize,
+        config: &ParticleConfig,
+    ) -> Result<(), JsValue> {
+        self.with_instance(instance_id, |instance| {
+            if instance.set_particle_config(config) {
+                Ok(())
+            } else {
+                Err(JsValue::from_str("Particle config only applies to Bg instances"))
+            }
+        })
+    }
 
-        let visualizer = if style_type == StyleType::Visualizer {
-            Some(Visualizer::new(canvas.clone())?)
-        } else {
-            None
+    #[wasm_bindgen]
+    pub fn export_preset(&self) -> Result<String, JsValue> {
+        let preset = Preset {
+            version: PRESET_VERSION,
+            settings: ProcessorOptions {
+                fft_size: Some(self.analyser.fft_size()),
+                smoothing: Some(self.analyser.smoothing_time_constant()),
+                reduced_motion: Some(self.reduced_motion),
+                max_fps: Some(self.max_fps.unwrap_or(0)),
+                adaptive_quality: Some(self.adaptive_quality),
+                color_by_pitch: Some(self.color_by_pitch),
+                show_album_art: Some(self.show_album_art),
+                onset_sensitivity: Some(self.onset_sensitivity()),
+                pan_visuals: Some(self.pan_visuals),
+                idle_animation: Some(self.idle_animation),
+                weighting: Some(self.weighting()),
+                camera: Some(self.camera.borrow().as_ref().map(Camera::config)),
+                ..ProcessorOptions::default()
+            },
+            instances: self
+                .instances
+                .borrow()
+                .iter()
+                .map(AudioVisualizerInstance::preset)
+                .collect(),
         };
 
-        let bg = if style_type == StyleType::Bg {
-            Some(Bg::new(canvas.clone())?)
-        } else {
-            None
-        };
+        let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+        let value = preset.serialize(&serializer)?;
+        js_sys::JSON::stringify(&value)?
+            .as_string()
+            .ok_or_else(|| JsValue::from_str("Failed to serialize preset"))
+    }
 
-        Ok(AudioVisualizerInstance {
-            visualizer,
-            bg,
-            style_type,
-            canvas,
-            ctx,
-        })
+    /// Instances cannot be created from a preset because they need a canvas, so
+    /// the preset must list exactly as many instances as are currently added.
+    /// On a mismatch nothing is applied and an error is returned.
+    #[wasm_bindgen]
+    pub fn import_preset(&mut self, json: &str) -> Result<(), JsValue> {
+        let value = js_sys::JSON::parse(json)?;
+        if !value.is_object() {
+            return Err(JsValue::from_str("Preset must be a JSON object"));
+        }
+        let preset: Preset = serde_wasm_bindgen::from_value(value)
+            .map_err(|e| JsValue::from_str(&format!("Invalid preset: {}", e)))?;
+        if preset.version > PRESET_VERSION {
+            return Err(JsValue::from_str("Preset was created by a newer version"));
+        }
+        let expected = self.instances.borrow().len();
+        if preset.instances.len() != expected {
+            return Err(JsValue::from_str(&format!(
+                "Preset has {} instances but {} are added",
+                preset.instances.len(),
+                expected
+            )));
+        }
+
+        self.apply_settings(&preset.settings)?;
+
+        for (instance, options) in self.instances.borrow_mut().iter_mut().zip(&preset.instances) {
+            instance.apply_preset(options)?;
+        }
+        Ok(())
     }
 
-    fn draw(&mut self, audio_data: &[u8]) {
-        match self.style_type {
-            StyleType::Visualizer => {
-                if let Some(ref mut visualizer) = self.visualizer {
-                    visualizer.draw(audio_data);
-                }
-            }
-            StyleType::Bg => {
-                if let Some(ref mut bg) = self.bg {
-                    bg.draw(audio_data);
+    #[wasm_bindgen]
+    pub fn enter_fullscreen(&mut self, instance_id: usize) -> Result<(), JsValue> {
+        let canvas = self
+            .instances
+            .borrow()
+            .get(instance_id)
+            .map(|instance| instance.canvas.clone())
+            .ok_or_else(|| JsValue::from_str("No instance with that id"))?;
+
+        if self.fullscreen_listener.is_none() {
+            let instances = self.instances.clone();
+            let on_change = Closure::wrap(Box::new(move || {
+                if let Err(e) = apply_fullscreen(&instances) {
+                    web_sys::console::error_1(&e);
                 }
-            }
+            }) as Box<dyn FnMut()>);
+            document()?.add_event_listener_with_callback(
+                "fullscreenchange",
+                on_change.as_ref().unchecked_ref(),
+            )?;
+            self.fullscreen_listener = Some(on_change);
         }
+
+        canvas.request_fullscreen()
     }
 
-    fn clear_canvas(&self) {
-        self.ctx.clear_rect(
-            0.0,
-            0.0,
-            self.canvas.width() as f64,
-            self.canvas.height() as f64,
-        );
+    #[wasm_bindgen]
+    pub fn exit_fullscreen(&self) -> Result<(), JsValue> {
+        let document = document()?;
+        if document.fullscreen_element().is_some() {
+            document.exit_fullscreen();
+        }
+        Ok(())
     }
-}
 
-fn log(s: &str) {
-    web_sys::console::log_1(&JsValue::from_str(s));
-}
+    #[wasm_bindgen]
+    pub fn set_on_audio_end(&mut self, callback: js_sys::Function) {
+        self.events.set(PlayerEvent::Ended, callback);
+    }
+
+    #[wasm_bindgen]
+    pub fn set_on_play(&mut self, callback: js_sys::Function) {
+        self.events.set(PlayerEvent::Play, callback);
+    }
+
+    #[wasm_bindgen]
+    pub fn set_on_pause(&mut self, callback: js_sys::Function) {
+        self.events.set(PlayerEvent::Pause, callback);
+    }
 
-async fn wait_for_updateend(source_buffer: &web_sys::SourceBuffer) -> Result<(), JsValue> {
-    use futures::channel::oneshot;
-    use wasm_bindgen::closure::Closure;
-    use wasm_bindgen::JsCast;
-    use std::cell::RefCell;
-    use std::rc::Rc;
+    #[wasm_bindgen]
+    pub fn set_on_error(&mut self, callback: js_sys::Function) {
+        self.events.set(PlayerEvent::Error, callback);
+    }
 
-    struct UpdateEndHandler {
-        closure: Closure<dyn FnMut()>,
+    #[wasm_bindgen]
+    pub fn set_on_buffering(&mut self, callback: js_sys::Function) {
+        self.events.set(PlayerEvent::Buffering, callback);
     }
 
-    impl UpdateEndHandler {
-        fn new(source_buffer: &web_sys::SourceBuffer) -> (Rc<RefCell<Option<Self>>>, oneshot::Receiver<()>) {
-            let (sender, receiver) = oneshot::channel::<()>();
-            let handler = Rc::new(RefCell::new(None));
+    #[wasm_bindgen]
+    pub fn set_on_stalled(&mut self, callback: js_sys::Function) {
+        self.events.set(PlayerEvent::Stalled, callback);
+    }
 
-            let handler_clone = handler.clone();
-            let sender = Rc::new(RefCell::new(Some(sender)));
+    #[wasm_bindgen]
+    pub fn set_on_recovered(&mut self, callback: js_sys::Function) {
+        self.events.set(PlayerEvent::Recovered, callback);
+    }
 
-            let closure = Closure::wrap(Box::new(move || {
-                if let Some(sender) = sender.borrow_mut().take() {
-                    let _ = sender.send(());
-                }
+    #[wasm_bindgen]
+    pub fn set_retry_policy(&mut self, config: Option<RetryConfig>) {
+        self.retry = config;
+    }
 
-                handler_clone.borrow_mut().take();
-            }) as Box<dyn FnMut()>);
+    #[wasm_bindgen]
+    pub fn set_on_waveform_ready(&mut self, callback: js_sys::Function) {
+        self.events.set(PlayerEvent::WaveformReady, callback);
+    }
+
+    #[wasm_bindgen]
+    pub fn get_waveform_peaks(&self, width: usize) -> Result<Vec<f32>, JsValue> {
+        let waveform = self
+            .waveform
+            .as_ref()
+            .ok_or_else(|| JsValue::from_str("No waveform available for the current track"))?;
+        Ok(waveform.peaks(width))
+    }
 
-            source_buffer.set_onupdateend(Some(closure.as_ref().unchecked_ref()));
+    #[wasm_bindgen]
+    pub async fn load_waveform(&mut self, path: &str) -> Result<(), JsValue> {
+        let array_buffer = fetch_array_buffer(&resolve_url(path), &RequestOptions::default()).await?;
+        let audio_buffer: web_sys::AudioBuffer =
+            JsFuture::from(self.context.decode_audio_data(&array_buffer)?).await?.dyn_into()?;
+        self.set_waveform(Some(Rc::new(Waveform::from_buffer(&audio_buffer)?)));
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub async fn render_offline(
+        &self,
+        path: &str,
+        fps: f64,
+        on_frame: js_sys::Function,
+        draw_instances: bool,
+    ) -> Result<usize, JsValue> {
+        let array_buffer = fetch_array_buffer(&resolve_url(path), &RequestOptions::default()).await?;
+        let audio_buffer: web_sys::AudioBuffer =
+            JsFuture::from(self.context.decode_audio_data(&array_buffer)?).await?.dyn_into()?;
+        let settings = offline::AnalyserSettings {
+            fft_size: self.analyser.fft_size(),
+            smoothing: self.analyser.smoothing_time_constant(),
+        };
 
-            *handler.borrow_mut() = Some(UpdateEndHandler { closure });
+        offline::render(&audio_buffer, &settings, fps, |frame, time, data| {
+            if draw_instances {
+                for instance in self.instances.borrow_mut().iter_mut() {
+                    instance.draw(data, None);
+                }
+            }
+            let result = on_frame.call3(
+                &JsValue::NULL,
+                &JsValue::from(frame as u32),
+                &JsValue::from(time),
+                &js_sys::Uint8Array::from(data),
+            )?;
+            Ok(result.dyn_into::<js_sys::Promise>().ok())
+        })
+        .await
+    }
 
-            (handler, receiver)
+    #[wasm_bindgen]
+    pub fn seek(&mut self, time: f64) -> Result<(), JsValue> {
+        if !time.is_finite() || time < 0.0 {
+            return Err(JsValue::from_str("Seek time must be a non-negative number"));
         }
+        if self.audio_element.borrow().is_none()
+            && self.playback_mode != Some(PlaybackMode::DecodeAudioData)
+        {
+            return Err(JsValue::from_str("Seeking is not supported for this source"));
+        }
+        seek_playback(&self.audio_element.borrow(), &mut self.buffer_track.borrow_mut(), time)
     }
 
-    let (_handler, receiver) = UpdateEndHandler::new(source_buffer);
+    #[wasm_bindgen]
+    pub fn set_source_gain(&self, source_id: usize, gain: f32) -> Result<(), JsValue> {
+        if !gain.is_finite() || gain < 0.0 {
+            return Err(JsValue::from_str("Source gain must be a non-negative number"));
+        }
+        self.mixer.set_gain(source_id, gain)
+    }
 
-    receiver.await.map_err(|_| JsValue::from_str("Failed to receive updateend event"))
+    #[wasm_bindgen]
+    pub fn set_loop_mode(&mut self, mode: LoopMode) -> Result<(), JsValue> {
+        if mode == LoopMode::Playlist && !self.looping.has_playlist() {
+            return Err(JsValue::from_str("Call set_playlist before looping a playlist"));
+        }
+        let position = self.current_time();
+        self.looping.set_mode(mode);
+        self.apply_loop(position)
+    }
+
+    #[wasm_bindgen]
+    pub fn loop_mode(&self) -> LoopMode {
+        self.looping.mode()
+    }
+
+    #[wasm_bindgen]
+    pub fn set_playlist(&mut self, length: usize, position: usize) -> Result<(), JsValue> {
+        if position >= length {
+            return Err(JsValue::from_str("Playlist position must be below its length"));
+        }
+        self.looping.set_playlist(length, position);
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn playlist_position(&self) -> usize {
+        self.looping.position()
+    }
+
+    #[wasm_bindgen]
+    pub fn set_ab_loop(&mut self, start_sec: f64, end_sec: f64) -> Result<(), JsValue> {
+        if !start_sec.is_finite() || !end_sec.is_finite() || start_sec < 0.0 || end_sec <= start_sec {
+            return Err(JsValue::from_str("A-B loop needs 0 <= start < end"));
+        }
+        let position = self.current_time();
+        self.looping.set_ab(Some((start_sec, end_sec)));
+        self.apply_loop(position)
+    }
+
+    #[wasm_bindgen]
+    pub fn clear_ab_loop(&mut self) -> Result<(), JsValue> {
+        let position = self.current_time();
+        self.looping.set_ab(None);
+        self.apply_loop(position)
+    }
+
+    #[wasm_bindgen]
+    pub fn seek_at(&mut self, instance_id: usize, x: f64) -> Result<(), JsValue> {
+        let width = self.with_instance(instance_id, |instance| Ok(instance.css_width))?;
+        if width == 0 {
+            return Ok(());
+        }
+        let duration = match self.waveform {
+            Some(ref waveform) => waveform.duration(),
+            None => self.duration(),
+        };
+        if !duration.is_finite() || duration <= 0.0 {
+            return Err(JsValue::from_str("Track duration is unknown"));
+        }
+        self.seek((x / width as f64).clamp(0.0, 1.0) * duration)
+    }
+
+    #[wasm_bindgen]
+    pub fn set_on_track_loaded(&mut self, callback: js_sys::Function) {
+        self.events.set(PlayerEvent::TrackLoaded, callback);
+    }
+
+    #[wasm_bindgen]
+    pub fn set_on_metadata(&mut self, callback: js_sys::Function) {
+        self.events.set(PlayerEvent::Metadata, callback);
+    }
+
+    #[wasm_bindgen]
+    pub fn set_on_onset(&mut self, callback: js_sys::Function) {
+        self.events.set(PlayerEvent::Onset, callback);
+    }
+
+    #[wasm_bindgen]
+    pub fn set_on_silence(&mut self, callback: js_sys::Function) {
+        self.events.set(PlayerEvent::Silence, callback);
+    }
+
+    #[wasm_bindgen]
+    pub fn set_on_signal(&mut self, callback: js_sys::Function) {
+        self.events.set(PlayerEvent::Signal, callback);
+    }
+
+    #[wasm_bindgen]
+    pub fn set_silence_detection(&mut self, config: Option<SilenceConfig>) {
+        *self.silence.borrow_mut() = config.map(SilenceDetector::new);
+    }
+
+    #[wasm_bindgen]
+    pub fn is_silent(&self) -> bool {
+        self.silence
+            .borrow()
+            .as_ref()
+            .is_some_and(SilenceDetector::is_silent)
+    }
+
+    #[wasm_bindgen]
+    pub fn set_onset_sensitivity(&mut self, sensitivity: f64) {
+        self.onset.borrow_mut().set_sensitivity(sensitivity);
+    }
+
+    #[wasm_bindgen]
+    pub fn onset_sensitivity(&self) -> f64 {
+        self.onset.borrow().sensitivity()
+    }
+
+    #[wasm_bindgen]
+    pub fn apply_weighting(&self, weighting: Weighting) {
+        self.weighting.borrow_mut().set_weighting(weighting);
+    }
+
+    #[wasm_bindgen]
+    pub fn weighting(&self) -> Weighting {
+        self.weighting.borrow().weighting()
+    }
+
+    #[wasm_bindgen]
+    pub fn features(&self) -> SpectralFeatures {
+        self.features.get()
+    }
+
+    #[wasm_bindgen]
+    pub fn get_pitch_hz(&self) -> Option<f64> {
+        self.pitch
+            .borrow_mut()
+            .detect(self.context.sample_rate() as f64)
+    }
+
+    #[wasm_bindgen]
+    pub fn get_pitch_note(&self) -> Option<PitchNote> {
+        self.get_pitch_hz().map(PitchNote::from_frequency)
+    }
+
+    #[wasm_bindgen]
+    pub fn set_color_by_pitch(&mut self, enabled: bool) {
+        self.color_by_pitch = enabled;
+        if !enabled {
+            for instance in self.instances.borrow_mut().iter_mut() {
+                instance.set_key_hue(None);
+            }
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn set_on_progress(&mut self, callback: js_sys::Function) {
+        self.events.set(PlayerEvent::Progress, callback);
+    }
+
+    #[wasm_bindgen]
+    pub fn current_time(&self) -> f64 {
+        if let Some(ref element) = *self.audio_element.borrow() {
+            return element.current_time();
+        }
+
+        let elapsed = self.context.current_time() - self.started_at;
+        match self.playback_mode {
+            Some(PlaybackMode::DecodeAudioData) => self
+                .buffer_track
+                .borrow()
+                .as_ref()
+                .map_or(0.0, BufferTrack::current_time),
+            Some(_) => elapsed,
+            None => 0.0,
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn duration(&self) -> f64 {
+        if let Some(ref element) = *self.audio_element.borrow() {
+            return element.duration();
+        }
+
+        match self.playback_mode {
+            Some(PlaybackMode::DecodeAudioData) => self
+                .buffer_track
+                .borrow()
+                .as_ref()
+                .map_or(0.0, BufferTrack::duration),
+            Some(PlaybackMode::Pcm) => self.pcm_next_start_time - self.started_at,
+            Some(_) => f64::INFINITY,
+            None => 0.0,
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn buffered_ranges(&self) -> Result<js_sys::Array, JsValue> {
+        let ranges = js_sys::Array::new();
+
+        if let Some(ref element) = *self.audio_element.borrow() {
+            let buffered = element.buffered();
+            for i in 0..buffered.length() {
+                ranges.push(&js_sys::Array::of2(
+                    &buffered.start(i)?.into(),
+                    &buffered.end(i)?.into(),
+                ));
+            }
+        } else if matches!(
+            self.playback_mode,
+            Some(PlaybackMode::DecodeAudioData) | Some(PlaybackMode::Pcm)
+        ) {
+            ranges.push(&js_sys::Array::of2(&0.0.into(), &self.duration().into()));
+        }
+
+        Ok(ranges)
+    }
+
+    #[wasm_bindgen]
+    pub fn set_text(
+        &self,
+        instance_id: usize,
+        text: &str,
+        config: &TextConfig,
+    ) -> Result<(), JsValue> {
+        let overlay = TextOverlay::new(text, config)?;
+        self.with_instance(instance_id, |instance| instance.set_text(Some(overlay)))
+    }
+
+    #[wasm_bindgen]
+    pub fn clear_text(&self, instance_id: usize) -> Result<(), JsValue> {
+        self.with_instance(instance_id, |instance| instance.set_text(None))
+    }
+
+    #[wasm_bindgen]
+    pub fn set_center_image(
+        &self,
+        instance_id: usize,
+        image: Option<web_sys::HtmlImageElement>,
+    ) -> Result<(), JsValue> {
+        self.with_instance(instance_id, |instance| {
+            instance.set_logo(image);
+            Ok(())
+        })
+    }
+
+    #[wasm_bindgen]
+    pub fn set_center_image_url(&self, instance_id: usize, url: &str) -> Result<(), JsValue> {
+        let image = web_sys::HtmlImageElement::new()?;
+        image.set_cross_origin(Some("anonymous"));
+        image.set_src(url);
+        self.set_center_image(instance_id, Some(image))
+    }
+
+    #[wasm_bindgen]
+    pub fn set_on_next_track(&mut self, callback: js_sys::Function) {
+        self.looping.set_next_track(Some(callback.clone()));
+        self.on_next_track = Some(callback);
+        self.refresh_media_session_handlers();
+    }
+
+    #[wasm_bindgen]
+    pub fn set_on_previous_track(&mut self, callback: js_sys::Function) {
+        self.on_previous_track = Some(callback);
+        self.refresh_media_session_handlers();
+    }
+
+    #[wasm_bindgen]
+    pub fn get_metadata(&self) -> JsValue {
+        self.metadata.to_js()
+    }
+
+    #[wasm_bindgen]
+    pub fn set_show_album_art(&mut self, enabled: bool) {
+        self.show_album_art = enabled;
+        let artwork = if enabled { self.metadata.artwork() } else { None };
+        for instance in self.instances.borrow_mut().iter_mut() {
+            instance.set_center_image(artwork.clone());
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn set_media_metadata(
+        &mut self,
+        title: &str,
+        artist: &str,
+        artwork_url: Option<String>,
+    ) -> Result<(), JsValue> {
+        media_session::set_metadata(title, artist, artwork_url)?;
+        self.register_media_session_handlers();
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub async fn enumerate_output_devices() -> Result<js_sys::Array, JsValue> {
+        output_device::enumerate_output_devices().await
+    }
+
+    #[wasm_bindgen]
+    pub async fn set_output_device(&mut self, device_id: &str) -> Result<(), JsValue> {
+        if output_device::supports_sink_id(&self.context) {
+            return output_device::set_sink_id(&self.context, device_id).await;
+        }
+
+        let sink_element = match self.sink_element {
+            Some(ref element) => element.clone(),
+            None => {
+                let element = self.create_sink_element()?;
+                self.sink_element = Some(element.clone());
+                element
+            }
+        };
+
+        if !output_device::supports_sink_id(&sink_element) {
+            return Err(JsValue::from_str("Output device selection is not supported"));
+        }
+
+        output_device::set_sink_id(&sink_element, device_id).await?;
+        JsFuture::from(sink_element.play()?).await?;
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn start_recording(
+        &mut self,
+        instance_id: usize,
+        options: Option<RecordingOptions>,
+    ) -> Result<(), JsValue> {
+        if self.recorder.is_some() {
+            return Err(JsValue::from_str("A recording is already in progress"));
+        }
+
+        let instances = self.instances.borrow();
+        let instance = instances
+            .get(instance_id)
+            .ok_or_else(|| JsValue::from_str("No instance with that id"))?;
+
+        let options = options.unwrap_or_default();
+        self.recorder = Some(Recorder::start(
+            &instance.canvas,
+            &self.context,
+            &self.analyser,
+            &options,
+        )?);
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub async fn stop_recording(&mut self) -> Result<web_sys::Blob, JsValue> {
+        let recorder = self
+            .recorder
+            .take()
+            .ok_or_else(|| JsValue::from_str("No recording in progress"))?;
+        recorder.stop(&self.analyser).await
+    }
+
+    #[wasm_bindgen]
+    pub async fn snapshot(
+        &self,
+        instance_id: usize,
+        scale: Option<f64>,
+    ) -> Result<web_sys::Blob, JsValue> {
+        let canvas = self
+            .instances
+            .borrow()
+            .get(instance_id)
+            .map(|instance| instance.canvas.clone())
+            .ok_or_else(|| JsValue::from_str("No instance with that id"))?;
+
+        snapshot::capture(&canvas, scale.unwrap_or(1.0)).await
+    }
+
+    #[wasm_bindgen]
+    pub fn pause(&self) -> Result<(), JsValue> {
+        pause_playback(&self.audio_element.borrow(), &self.context)?;
+        media_session::set_playback_state(false);
+        if self.audio_element.borrow().is_none() {
+            self.events.emit(PlayerEvent::Pause);
+        }
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn resume(&self) -> Result<(), JsValue> {
+        resume_playback(&self.audio_element.borrow(), &self.context)?;
+        media_session::set_playback_state(true);
+        if self.audio_element.borrow().is_none() {
+            self.events.emit(PlayerEvent::Play);
+        }
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub async fn process_audio_from_path(
+        &mut self,
+        path: &str,
+        options: Option<FetchOptions>,
+    ) -> Result<usize, JsValue> {
+        let server_url = resolve_url(path);
+        let request = options
+            .map(RequestOptions::from_options)
+            .transpose()?
+            .unwrap_or_default();
+
+        if let Some(preloaded) = self.take_preloaded(&server_url, &request) {
+            return self.play_preloaded(preloaded).await;
+        }
+
+        if hls::is_playlist_url(&server_url) {
+            return self.play_hls(server_url, request).await;
+        }
+
+        if media_source_supported(STREAM_MIME_TYPE) {
+            let options = StreamOptions {
+                request,
+                ..StreamOptions::default()
+            };
+            self.stream_from_url(&server_url, options).await
+        } else {
+            log("MediaSource unavailable, falling back to decodeAudioData");
+            self.decode_from_url(&server_url, &request).await
+        }
+    }
+
+    #[wasm_bindgen]
+    pub async fn preload(
+        &mut self,
+        path: &str,
+        options: Option<FetchOptions>,
+    ) -> Result<(), JsValue> {
+        let url = resolve_url(path);
+        let request = options
+            .map(RequestOptions::from_options)
+            .transpose()?
+            .unwrap_or_default();
+        self.clear_preload();
+
+        let metadata = SharedMetadata::standby();
+        let standby = if hls::is_playlist_url(&url) && has_media_source() {
+            let playlist_url = url.clone();
+            let events = self.events.clone();
+            let options = StreamOptions {
+                retention: self.buffer_retention,
+                looping: self.looping.clone(),
+                request: request.clone(),
+                retry: self.retry,
+                ..StreamOptions::default()
+            };
+            let (element, bus) = self.attach_media_source(move |window, media_source, audio_element| {
+                hls::pump_playlist(window, media_source, audio_element, playlist_url, options, events)
+            })?;
+            Standby::Element(element, bus, PlaybackMode::MediaSource)
+        } else if hls::is_playlist_url(&url) {
+            if !request.is_default() {
+                return Err(JsValue::from_str(NATIVE_HLS_REQUEST_ERROR));
+            }
+            let (element, bus) = self.create_audio_element(&url)?;
+            if element.can_play_type(hls::PLAYLIST_MIME_TYPE).is_empty() {
+                return Err(JsValue::from_str("HLS playback is not supported in this browser"));
+            }
+            Standby::Element(element, bus, PlaybackMode::Native)
+        } else if media_source_supported(STREAM_MIME_TYPE) {
+            let server_url = url.clone();
+            let events = self.events.clone();
+            let stream_metadata = metadata.clone();
+            let options = StreamOptions {
+                retention: self.buffer_retention,
+                looping: self.looping.clone(),
+                request: request.clone(),
+                retry: self.retry,
+                ..StreamOptions::default()
+            };
+            let (element, bus) = self.attach_media_source(move |window, media_source, audio_element| {
+                stream::pump_stream(
+                    window,
+                    media_source,
+                    audio_element,
+                    server_url,
+                    options,
+                    events,
+                    stream_metadata,
+                )
+            })?;
+            Standby::Element(element, bus, PlaybackMode::MediaSource)
+        } else {
+            let array_buffer = fetch_array_buffer(&url, &request).await?;
+            Standby::Buffer(self.decode_array_buffer(&array_buffer, &metadata).await?)
+        };
+
+        if let Standby::Element(ref element, _, _) = standby {
+            element.set_preload("auto");
+        }
+        log(&format!("Preloading {}", url));
+        self.preloaded = Some(Preloaded {
+            url,
+            request,
+            standby,
+            metadata,
+        });
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn clear_preload(&mut self) {
+        if let Some(preloaded) = self.preloaded.take() {
+            preloaded.discard();
+        }
+    }
+
+    #[wasm_bindgen]
+    pub async fn process_audio_from_radio(
+        &mut self,
+        url: &str,
+        request_metadata: bool,
+    ) -> Result<usize, JsValue> {
+        if !media_source_supported(STREAM_MIME_TYPE) {
+            return Err(JsValue::from_str("Live streams require MediaSource support"));
+        }
+
+        let options = StreamOptions {
+            live: true,
+            icy_metadata: request_metadata,
+            ..StreamOptions::default()
+        };
+        self.stream_from_url(&resolve_url(url), options).await
+    }
+
+    #[wasm_bindgen]
+    pub async fn process_audio_from_hls(&mut self, url: &str) -> Result<usize, JsValue> {
+        self.play_hls(resolve_url(url), RequestOptions::default()).await
+    }
+
+    #[wasm_bindgen]
+    pub async fn process_audio_from_websocket(&mut self, url: &str) -> Result<usize, JsValue> {
+        if !media_source_supported(STREAM_MIME_TYPE) {
+            return Err(JsValue::from_str("WebSocket streaming requires MediaSource support"));
+        }
+
+        let url = url.to_string();
+        let events = self.events.clone();
+        let options = StreamOptions {
+            live: true,
+            retention: self.buffer_retention,
+            looping: self.looping.clone(),
+            retry: self.retry,
+            ..StreamOptions::default()
+        };
+        self.play_with_media_source(move |window, media_source, audio_element| {
+            stream::pump_websocket(window, media_source, audio_element, url, options, events)
+        })
+        .await
+    }
+
+    #[wasm_bindgen]
+    pub async fn process_audio_from_blob(&mut self, file: web_sys::File) -> Result<usize, JsValue> {
+        log(&format!("Decoding local file {}", file.name()));
+
+        let array_buffer: js_sys::ArrayBuffer =
+            JsFuture::from(file.array_buffer()).await?.dyn_into()?;
+
+        self.play_array_buffer(&array_buffer).await
+    }
+
+    #[wasm_bindgen]
+    pub fn process_audio_from_pcm(
+        &mut self,
+        samples: &[f32],
+        sample_rate: u32,
+        channels: u32,
+    ) -> Result<usize, JsValue> {
+        if channels == 0 {
+            return Err(JsValue::from_str("PCM input needs at least one channel"));
+        }
+
+        let (id, bus) = match self.pcm_bus {
+            Some(ref pcm_bus) => pcm_bus.clone(),
+            None => {
+                let bus = self.context.create_gain()?;
+                let id = self.add_source(bus.clone(), true)?;
+                let _ = self.context.resume()?;
+                self.pcm_bus = Some((id, bus.clone()));
+                (id, bus)
+            }
+        };
+
+        let frames = samples.len() / channels as usize;
+        if frames == 0 {
+            return Ok(id);
+        }
+
+        let buffer = self
+            .context
+            .create_buffer(channels, frames as u32, sample_rate as f32)?;
+        let mut channel_data = vec![0.0f32; frames];
+        for channel in 0..channels as usize {
+            for (frame, sample) in channel_data.iter_mut().enumerate() {
+                *sample = samples[frame * channels as usize + channel];
+            }
+            buffer.copy_to_channel(&channel_data, channel as i32)?;
+        }
+
+        let source = self.context.create_buffer_source()?;
+        source.set_buffer(Some(&buffer));
+        source.connect_with_audio_node(&bus)?;
+
+        let start_time = self.pcm_next_start_time.max(self.context.current_time());
+        if self.playback_mode != Some(PlaybackMode::Pcm) {
+            self.started_at = start_time;
+            self.events.emit(PlayerEvent::Play);
+        }
+        AudioScheduledSourceNode::start_with_when(&source, start_time)?;
+        self.pcm_next_start_time = start_time + buffer.duration();
+
+        self.is_playing = true;
+        self.playback_mode = Some(PlaybackMode::Pcm);
+
+        Ok(id)
+    }
+
+    #[wasm_bindgen]
+    pub fn process_audio_from_stream(&mut self, stream: MediaStream) -> Result<usize, JsValue> {
+        log("Attaching MediaStream to analyser");
+
+        let bus = self.context.create_gain()?;
+        self.context
+            .create_media_stream_source(&stream)?
+            .connect_with_audio_node(&bus)?;
+        // The stream is already audible wherever it came from (a call, a mic),
+        // so keep it off the output bus to avoid echo and feedback.
+        let id = self.add_source(bus, false)?;
+        if let Some(ended) = self.mixer.ended_flag(id) {
+            let on_inactive = Closure::wrap(Box::new(move || ended.set(true)) as Box<dyn FnMut()>);
+            stream.add_event_listener_with_callback("inactive", on_inactive.as_ref().unchecked_ref())?;
+            on_inactive.forget();
+        }
+        let _ = self.context.resume()?;
+
+        if !self.is_playing {
+            self.started_at = self.context.current_time();
+            self.is_playing = true;
+            self.playback_mode = Some(PlaybackMode::MediaStream);
+            self.events.emit(PlayerEvent::Play);
+        }
+
+        Ok(id)
+    }
+
+    #[wasm_bindgen]
+    pub fn playback_mode(&self) -> Option<PlaybackMode> {
+        self.playback_mode
+    }
+
+    #[wasm_bindgen]
+    pub fn stop_audio(&mut self) -> Result<(), JsValue> {
+        self.is_playing = false;
+        self.playback_mode = None;
+        self.audio_element.borrow_mut().take();
+        self.metadata.clear();
+        self.set_waveform(None);
+        self.looping.set_ab(None);
+
+        if let Some(mut track) = self.buffer_track.borrow_mut().take() {
+            track.stop()?;
+        }
+
+        self.pcm_bus = None;
+        self.pcm_next_start_time = 0.0;
+        self.mixer.clear();
+        self.unbind_sources(|_| true);
+
+        if let Some(window) = web_sys::window() {
+            if let Some(document) = window.document() {
+                let audio_elements = document.get_elements_by_tag_name("audio");
+                let length = audio_elements.length();
+                for i in (0..length).rev() {
+                    if let Some(audio) = audio_elements.item(i) {
+                        if let Some(media) = audio.dyn_ref::<HtmlMediaElement>() {
+                            let _ = media.pause();
+                            media.remove_attribute("src")?;
+                            media.load();
+                        }
+                        if let Some(parent) = audio.parent_node() {
+                            parent.remove_child(&audio)?;
+                        }
+                    }
+                }
+            }
+        }
+
+        self.clear_all();
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn draw(&self) {
+        if !self.is_playing {
+            if self.idle_animation && !self.is_page_hidden() {
+                self.draw_idle();
+            }
+            return;
+        }
+        if let Some(ref element) = *self.audio_element.borrow() {
+            wrap_ab_loop(element, &self.looping);
+        }
+        if self.is_page_hidden() {
+            return;
+        }
+
+        let frame_start = now_ms();
+        if !self.frame_due(frame_start) {
+            return;
+        }
+
+        let buffer_length = self.analyser.frequency_bin_count() as usize;
+        let mut data_array = self.frequency_data.borrow_mut();
+        if data_array.len() != buffer_length {
+            data_array.resize(buffer_length, 0);
+        }
+        match self.worklet {
+            Some(ref worklet) => worklet.read_frequency(&mut data_array),
+            None => self.analyser.get_byte_frequency_data(&mut data_array),
+        }
+        self.apply_weighting_curve(&mut data_array);
+        if let Some(ref output) = self.shared_output {
+            if let Err(e) = output.write(&data_array) {
+                web_sys::console::error_1(&e);
+            }
+        }
+        if let Some(ref mut normalizer) = *self.normalizer.borrow_mut() {
+            self.mixer.set_level(normalizer.update(&self.analyser) as f32);
+        }
+
+        self.report_progress();
+
+        let silence = self.silence.borrow_mut().as_mut().map(|detector| {
+            let change = detector.process(&self.analyser, frame_start);
+            (change, detector.pauses_rendering())
+        });
+        if let Some((change, paused)) = silence {
+            match change {
+                Some(SilenceChange::Silence) => self.events.emit(PlayerEvent::Silence),
+                Some(SilenceChange::Signal) => self.events.emit(PlayerEvent::Signal),
+                None => {}
+            }
+            if paused {
+                return;
+            }
+        }
+
+        let (onset, flux) = {
+            let mut detector = self.onset.borrow_mut();
+            (detector.process(&data_array, frame_start), detector.flux())
+        };
+        if let Some(strength) = onset {
+            self.events.emit1(PlayerEvent::Onset, &strength.into());
+        }
+        let nyquist = self.context.sample_rate() as f64 / 2.0;
+        let features = SpectralFeatures::compute(&data_array, nyquist, flux);
+        self.features.set(features);
+
+        let camera = match *self.camera.borrow_mut() {
+            Some(ref mut camera) if !self.reduced_motion_enabled() => {
+                Some(camera.update(&data_array, nyquist))
+            }
+            _ => None,
+        };
+
+        let key_hue = if self.color_by_pitch {
+            self.get_pitch_hz()
+                .map(|frequency| pitch::pitch_class(frequency) as f64 * 30.0)
+        } else {
+            None
+        };
+
+        let progress = self
+            .waveform
+            .as_ref()
+            .filter(|waveform| waveform.duration() > 0.0)
+            .map(|waveform| self.current_time() / waveform.duration());
+
+        {
+            let mut instances = self.instances.borrow_mut();
+            if let Some(artwork) = self.metadata.take_artwork_change() {
+                let artwork = artwork.filter(|_| self.show_album_art);
+                for instance in instances.iter_mut() {
+                    instance.set_center_image(artwork.clone());
+                }
+            }
+            for instance in instances.iter_mut() {
+                if onset.is_some() {
+                    instance.trigger_onset();
+                }
+                if key_hue.is_some() {
+                    instance.set_key_hue(key_hue);
+                }
+                instance.set_features(&features);
+                if let Some(progress) = progress {
+                    instance.set_progress(progress);
+                }
+                match instance.source {
+                    Some(source_id) => {
+                        let mut source_data = self.source_frequency_data.borrow_mut();
+                        self.read_source_frequency(source_id, buffer_length, &mut source_data);
+                        instance.draw(&source_data, camera);
+                    }
+                    None => instance.draw(&data_array, camera),
+                }
+            }
+        }
+
+        if self.adaptive_quality {
+            self.adapt_quality(now_ms() - frame_start);
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn clear_all(&self) {
+        let mut instances = self.instances.borrow_mut();
+        for instance in instances.iter_mut() {
+            instance.clear_canvas();
+        }
+    }
+}
+
+impl SharedAudioProcessor {
+    fn insert_instance(
+        &mut self,
+        canvas: HtmlCanvasElement,
+        style_type: StyleType,
+        source: Option<usize>,
+    ) -> Result<usize, JsValue> {
+        let mut instance = AudioVisualizerInstance::new(canvas.clone(), style_type)?;
+        instance.source = source;
+        instance.resize()?;
+        instance.set_reduced_motion(self.reduced_motion_enabled());
+        instance.set_quality(self.quality.get());
+        if self.show_album_art {
+            instance.set_center_image(self.metadata.artwork());
+        }
+        instance.set_waveform(self.waveform.clone());
+        instance.set_pan(self.visual_pan());
+        if let Some(seed) = self.seed {
+            instance.set_seed(seed);
+        }
+        let id = {
+            let mut instances = self.instances.borrow_mut();
+            instances.push(instance);
+            instances.len() - 1
+        };
+        self.observe_resize(&canvas)?;
+        Ok(id)
+    }
+
+    fn register_media_session_handlers(&mut self) {
+        self.media_session_handlers = media_session::register_handlers(
+            self.audio_element.clone(),
+            self.buffer_track.clone(),
+            self.context.clone(),
+            self.on_next_track.clone(),
+            self.on_previous_track.clone(),
+        );
+    }
+
+    // Handlers capture the track callbacks, so swap them in once the session is in use.
+    fn refresh_media_session_handlers(&mut self) {
+        if self.media_session_handlers.is_some() {
+            self.register_media_session_handlers();
+        }
+    }
+
+    fn apply_settings(&mut self, options: &ProcessorOptions) -> Result<(), JsValue> {
+        if options
+            .fft_size
+            .is_some_and(|size| !size.is_power_of_two() || !(32..=32768).contains(&size))
+        {
+            return Err(JsValue::from_str("FFT size must be a power of two between 32 and 32768"));
+        }
+        if let Some(size) = options.fft_size {
+            if let Some(ref output) = self.shared_output {
+                output.check_capacity(size as usize / 2)?;
+            }
+            self.analyser.set_fft_size(size);
+        }
+        if let Some(smoothing) = options.smoothing {
+            self.analyser.set_smoothing_time_constant(smoothing.clamp(0.0, 1.0));
+        }
+        if let Some(enabled) = options.reduced_motion {
+            self.set_reduced_motion(enabled);
+        }
+        if let Some(fps) = options.max_fps {
+            self.set_max_fps(fps);
+        }
+        if let Some(enabled) = options.adaptive_quality {
+            self.set_adaptive_quality(enabled);
+        }
+        if let Some(enabled) = options.color_by_pitch {
+            self.set_color_by_pitch(enabled);
+        }
+        if let Some(enabled) = options.show_album_art {
+            self.set_show_album_art(enabled);
+        }
+        if let Some(sensitivity) = options.onset_sensitivity {
+            self.set_onset_sensitivity(sensitivity);
+        }
+        if let Some(enabled) = options.pan_visuals {
+            self.set_pan_visuals(enabled);
+        }
+        if let Some(enabled) = options.idle_animation {
+            self.set_idle_animation(enabled);
+        }
+        if let Some(weighting) = options.weighting {
+            self.apply_weighting(weighting);
+        }
+        if let Some(camera) = options.camera {
+            self.set_camera_effect(camera);
+        }
+        Ok(())
+    }
+
+    fn add_source(&mut self, bus: GainNode, audible: bool) -> Result<usize, JsValue> {
+        let pruned = self.mixer.prune();
+        self.unbind_sources(|id| pruned.contains(&id));
+        self.mixer.add(bus, audible)
+    }
+
+    fn unbind_sources(&self, removed: impl Fn(usize) -> bool) {
+        for instance in self.instances.borrow_mut().iter_mut() {
+            if instance.source.is_some_and(&removed) {
+                instance.source = None;
+            }
+        }
+    }
+
+    fn with_instance<T>(
+        &self,
+        instance_id: usize,
+        f: impl FnOnce(&mut AudioVisualizerInstance) -> Result<T, JsValue>,
+    ) -> Result<T, JsValue> {
+        let mut instances = self.instances.borrow_mut();
+        let instance = instances
+            .get_mut(instance_id)
+            .ok_or_else(|| JsValue::from_str("No instance with that id"))?;
+        f(instance)
+    }
+
+    fn apply_loop(&mut self, position: f64) -> Result<(), JsValue> {
+        let buffer_source = self.playback_mode == Some(PlaybackMode::DecodeAudioData)
+            && self.buffer_track.borrow().is_some();
+        if self.audio_element.borrow().is_none() && !buffer_source {
+            return Ok(());
+        }
+
+        let target = match self.looping.ab() {
+            Some((start, end)) if position < start || position >= end => start,
+            _ => position,
+        };
+        // Buffer sources loop natively, so restart them to pick up the new loop points.
+        if buffer_source || target != position {
+            self.seek(target)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn read_source_frequency(&self, source_id: usize, length: usize, data: &mut Vec<u8>) {
+        data.resize(length, 0);
+        match self.mixer.analyser(source_id) {
+            Some(analyser) => analyser.get_byte_frequency_data(data),
+            // stop_audio and pruning unbind instances, so this only catches an id that
+            // was never attached; show silence rather than the mix.
+            None => data.fill(0),
+        }
+        self.apply_weighting_curve(data);
+    }
+
+    fn apply_weighting_curve(&self, data: &mut [u8]) {
+        let nyquist = self.context.sample_rate() as f64 / 2.0;
+        self.weighting.borrow_mut().apply(
+            data,
+            nyquist,
+            self.analyser.min_decibels(),
+            self.analyser.max_decibels(),
+        );
+    }
+
+    fn frame_due(&self, now: f64) -> bool {
+        if let Some(max_fps) = self.max_fps {
+            // Allow a little jitter so a 60 fps cap on a 60 Hz display does not drop every other frame.
+            let interval = 1000.0 / max_fps as f64;
+            if now - self.last_frame_at.get() < interval * 0.9 {
+                return false;
+            }
+        }
+        self.last_frame_at.set(now);
+        true
+    }
+
+    fn draw_idle(&self) {
+        let frame_start = now_ms();
+        if !self.frame_due(frame_start) {
+            return;
+        }
+
+        let mut data = self.frequency_data.borrow_mut();
+        data.resize(self.analyser.frequency_bin_count() as usize, 0);
+        idle::ambient_spectrum(&mut data, frame_start);
+
+        let nyquist = self.context.sample_rate() as f64 / 2.0;
+        let features = SpectralFeatures::compute(&data, nyquist, 0.0);
+        for instance in self.instances.borrow_mut().iter_mut() {
+            instance.set_features(&features);
+            instance.draw(&data, None);
+        }
+    }
+
+    fn visual_pan(&self) -> f64 {
+        if self.pan_visuals {
+            self.pan as f64
+        } else {
+            0.0
+        }
+    }
+
+    fn apply_visual_pan(&self) {
+        let pan = self.visual_pan();
+        for instance in self.instances.borrow_mut().iter_mut() {
+            instance.set_pan(pan);
+        }
+    }
+
+    fn adapt_quality(&self, frame_ms: f64) {
+        let cost = self.frame_cost.get() * 0.9 + frame_ms * 0.1;
+        self.frame_cost.set(cost);
+
+        // Leave half of each frame for the browser and the audio pipeline.
+        let budget = self
+            .max_fps
+            .map(|fps| 1000.0 / fps as f64)
+            .unwrap_or(DEFAULT_FRAME_BUDGET_MS)
+            * 0.5;
+
+        let quality = self.quality.get();
+        if cost > budget && quality > MIN_QUALITY {
+            self.apply_quality((quality * 0.9).max(MIN_QUALITY));
+        } else if cost < budget * 0.5 && quality < 1.0 {
+            self.apply_quality((quality + 0.02).min(1.0));
+        }
+    }
+
+    fn apply_quality(&self, quality: f64) {
+        self.quality.set(quality);
+        for instance in self.instances.borrow_mut().iter_mut() {
+            instance.set_quality(quality);
+        }
+    }
+
+    fn is_page_hidden(&self) -> bool {
+        self.visibility
+            .as_ref()
+            .map(|visibility| visibility.is_hidden())
+            .unwrap_or(false)
+    }
+
+    fn observe_resize(&mut self, canvas: &HtmlCanvasElement) -> Result<(), JsValue> {
+        if self.resize_observer.is_none() {
+            let instances = self.instances.clone();
+            let on_resize = Closure::wrap(Box::new(move || {
+                for instance in instances.borrow_mut().iter_mut() {
+                    if let Err(e) = instance.resize() {
+                        web_sys::console::error_1(&e);
+                    }
+                }
+            }) as Box<dyn FnMut()>);
+            let observer = web_sys::ResizeObserver::new(on_resize.as_ref().unchecked_ref())?;
+            self.resize_observer = Some((observer, on_resize));
+        }
+
+        if let Some((ref observer, _)) = self.resize_observer {
+            observer.observe(canvas);
+        }
+        Ok(())
+    }
+
+    fn report_progress(&self) {
+        if !self.events.has(PlayerEvent::Progress) || self.audio_element.borrow().is_some() {
+            return;
+        }
+
+        let now = self.context.current_time();
+        if now - self.last_progress_at.get() < PROGRESS_INTERVAL_SECONDS {
+            return;
+        }
+        self.last_progress_at.set(now);
+
+        self.events.emit2(
+            PlayerEvent::Progress,
+            &self.current_time().into(),
+            &self.duration().into(),
+        );
+    }
+
+    fn connect_output(&self) -> Result<(), JsValue> {
+        let output: &web_sys::AudioNode = match self.compressor {
+            Some(ref compressor) => {
+                self.mixer.output().connect_with_audio_node(compressor)?;
+                compressor
+            }
+            None => self.mixer.output(),
+        };
+        match self.sink_destination {
+            Some(ref destination) => output.connect_with_audio_node(destination)?,
+            None => output.connect_with_audio_node(&self.context.destination())?,
+        };
+        Ok(())
+    }
+
+    fn disconnect_output(&self) -> Result<(), JsValue> {
+        self.mixer.output().disconnect()?;
+        if let Some(ref compressor) = self.compressor {
+            compressor.disconnect()?;
+        }
+        Ok(())
+    }
+
+    fn create_sink_element(&mut self) -> Result<HtmlMediaElement, JsValue> {
+        let document = web_sys::window()
+            .and_then(|window| window.document())
+            .ok_or_else(|| JsValue::from_str("No document found"))?;
+
+        let destination = self.context.create_media_stream_destination()?;
+        let element: HtmlMediaElement = document.create_element("audio")?.dyn_into()?;
+        element.set_src_object(Some(&destination.stream()));
+
+        self.disconnect_output()?;
+        self.sink_destination = Some(destination);
+        self.connect_output()?;
+
+        Ok(element)
+    }
+
+    async fn play_hls(
+        &mut self,
+        playlist_url: String,
+        request: RequestOptions,
+    ) -> Result<usize, JsValue> {
+        if has_media_source() {
+            log("Starting HLS playlist streaming");
+            let events = self.events.clone();
+            let options = StreamOptions {
+                retention: self.buffer_retention,
+                looping: self.looping.clone(),
+                request,
+                retry: self.retry,
+                ..StreamOptions::default()
+            };
+            self.play_with_media_source(move |window, media_source, audio_element| {
+                hls::pump_playlist(window, media_source, audio_element, playlist_url, options, events)
+            })
+            .await
+        } else {
+            if !request.is_default() {
+                return Err(JsValue::from_str(NATIVE_HLS_REQUEST_ERROR));
+            }
+            let (audio_element, bus) = self.create_audio_element(&playlist_url)?;
+            if audio_element.can_play_type(hls::PLAYLIST_MIME_TYPE).is_empty() {
+                return Err(JsValue::from_str("HLS playback is not supported in this browser"));
+            }
+
+            log("MediaSource unavailable, using native HLS playback");
+            self.start_audio_element(&audio_element, bus, PlaybackMode::Native)
+                .await
+        }
+    }
+
+    async fn stream_from_url(
+        &mut self,
+        server_url: &str,
+        options: StreamOptions,
+    ) -> Result<usize, JsValue> {
+        log("Starting streaming audio processing");
+
+        let server_url = server_url.to_string();
+        let events = self.events.clone();
+        let metadata = self.metadata.clone();
+        let options = StreamOptions {
+            retention: self.buffer_retention,
+            looping: self.looping.clone(),
+            retry: self.retry,
+            ..options
+        };
+
+        self.play_with_media_source(move |window, media_source, audio_element| {
+            stream::pump_stream(
+                window,
+                media_source,
+                audio_element,
+                server_url,
+                options,
+                events,
+                metadata,
+            )
+        })
+        .await
+    }
+
+    async fn play_with_media_source<F, Fut>(&mut self, pump: F) -> Result<usize, JsValue>
+    where
+        F: FnOnce(Window, MediaSource, HtmlMediaElement) -> Fut + 'static,
+        Fut: Future<Output = Result<(), JsValue>> + 'static,
+    {
+        let (audio_element, bus) = self.attach_media_source(pump)?;
+        self.start_audio_element(&audio_element, bus, PlaybackMode::MediaSource)
+            .await
+    }
+
+    fn attach_media_source<F, Fut>(&mut self, pump: F) -> Result<(HtmlMediaElement, GainNode), JsValue>
+    where
+        F: FnOnce(Window, MediaSource, HtmlMediaElement) -> Fut + 'static,
+        Fut: Future<Output = Result<(), JsValue>> + 'static,
+    {
+        let window = web_sys::window().ok_or_else(|| JsValue::from_str("No window found"))?;
+
+        let media_source = MediaSource::new()?;
+        let media_url = web_sys::Url::create_object_url_with_source(&media_source)?;
+        let (audio_element, bus) = self.create_audio_element(&media_url)?;
+
+        let media_source_clone = media_source.clone();
+        let audio_element_clone = audio_element.clone();
+        let events = self.events.clone();
+
+        let on_source_open = Closure::once(move || {
+            wasm_bindgen_futures::spawn_local(async move {
+                if let Err(e) = pump(window, media_source_clone, audio_element_clone).await {
+                    events.error(&e);
+                }
+            });
+        });
+        media_source.set_onsourceopen(Some(on_source_open.as_ref().unchecked_ref()));
+        on_source_open.forget();
+
+        Ok((audio_element, bus))
+    }
+
+    fn create_audio_element(&mut self, src: &str) -> Result<(HtmlMediaElement, GainNode), JsValue> {
+        let document = web_sys::window()
+            .and_then(|window| window.document())
+            .ok_or_else(|| JsValue::from_str("No document found"))?;
+        let audio_element: HtmlMediaElement = document.create_element("audio")?.dyn_into()?;
+
+        audio_element.set_src(src);
+        audio_element.set_cross_origin(Some("anonymous"));
+
+        let bus = self.context.create_gain()?;
+        self.context
+            .create_media_element_source(&audio_element)?
+            .connect_with_audio_node(&bus)?;
+
+        Ok((audio_element, bus))
+    }
+
+    fn attach_element_events(&self, audio_element: &HtmlMediaElement, ended: Option<Rc<Cell<bool>>>) {
+        let on_ended =
+            ended_callback(&self.events, &self.looping, Some(audio_element.clone()), ended);
+        audio_element.set_onended(Some(on_ended.as_ref().unchecked_ref()));
+        on_ended.forget();
+
+        let on_play = self.events.listener(|events| events.emit(PlayerEvent::Play));
+        audio_element.set_onplay(Some(on_play.as_ref().unchecked_ref()));
+        on_play.forget();
+
+        let on_pause = self.events.listener(|events| events.emit(PlayerEvent::Pause));
+        audio_element.set_onpause(Some(on_pause.as_ref().unchecked_ref()));
+        on_pause.forget();
+
+        let on_waiting = self
+            .events
+            .listener(|events| events.emit1(PlayerEvent::Buffering, &true.into()));
+        audio_element.set_onwaiting(Some(on_waiting.as_ref().unchecked_ref()));
+        on_waiting.forget();
+
+        let on_playing = self
+            .events
+            .listener(|events| events.emit1(PlayerEvent::Buffering, &false.into()));
+        audio_element.set_onplaying(Some(on_playing.as_ref().unchecked_ref()));
+        on_playing.forget();
+
+        let element = audio_element.clone();
+        let on_loaded_metadata = self.events.listener(move |events| {
+            events.emit1(PlayerEvent::TrackLoaded, &element.duration().into());
+        });
+        audio_element.set_onloadedmetadata(Some(on_loaded_metadata.as_ref().unchecked_ref()));
+        on_loaded_metadata.forget();
+
+        let element = audio_element.clone();
+        let looping = self.looping.clone();
+        let on_time_update = self.events.listener(move |events| {
+            wrap_ab_loop(&element, &looping);
+            events.emit2(
+                PlayerEvent::Progress,
+                &element.current_time().into(),
+                &element.duration().into(),
+            );
+        });
+        audio_element.set_ontimeupdate(Some(on_time_update.as_ref().unchecked_ref()));
+        on_time_update.forget();
+
+        let element = audio_element.clone();
+        let on_error = self.events.listener(move |events| {
+            let error = js_sys::Reflect::get(&element, &"error".into()).unwrap_or(JsValue::NULL);
+            events.error(&error);
+        });
+        audio_element.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+        on_error.forget();
+    }
+
+    async fn start_audio_element(
+        &mut self,
+        audio_element: &HtmlMediaElement,
+        bus: GainNode,
+        mode: PlaybackMode,
+    ) -> Result<usize, JsValue> {
+        let id = self.add_source(bus, true)?;
+        self.attach_element_events(audio_element, self.mixer.ended_flag(id));
+        // A preloaded element has already read its metadata before the handler existed.
+        if audio_element.ready_state() >= HtmlMediaElement::HAVE_METADATA {
+            self.events
+                .emit1(PlayerEvent::TrackLoaded, &audio_element.duration().into());
+        }
+
+        let _ = self.context.resume()?;
+        let play_promise = audio_element.play()?;
+        JsFuture::from(play_promise).await?;
+
+        self.is_playing = true;
+        self.playback_mode = Some(mode);
+        *self.audio_element.borrow_mut() = Some(audio_element.clone());
+        media_session::set_playback_state(true);
+
+        audio_element.set_attribute("style", "display: none")?;
+        web_sys::window()
+            .and_then(|window| window.document())
+            .and_then(|document| document.body())
+            .ok_or_else(|| JsValue::from_str("No body found"))?
+            .append_child(audio_element)?;
+
+        Ok(id)
+    }
+
+    async fn decode_from_url(
+        &mut self,
+        url: &str,
+        request: &RequestOptions,
+    ) -> Result<usize, JsValue> {
+        log("Fetching whole file for decodeAudioData playback");
+
+        let array_buffer = fetch_array_buffer(url, request).await?;
+        self.play_array_buffer(&array_buffer).await
+    }
+
+    async fn play_array_buffer(&mut self, data: &js_sys::ArrayBuffer) -> Result<usize, JsValue> {
+        let audio_buffer = self.decode_array_buffer(data, &self.metadata).await?;
+        self.play_audio_buffer(&audio_buffer).await
+    }
+
+    async fn decode_array_buffer(
+        &self,
+        data: &js_sys::ArrayBuffer,
+        metadata: &SharedMetadata,
+    ) -> Result<web_sys::AudioBuffer, JsValue> {
+        let bytes = js_sys::Uint8Array::new(data);
+        let header = bytes.subarray(0, 10).to_vec();
+        if let Some(size) = id3::tag_size(&header).filter(|&size| size <= bytes.length() as usize) {
+            if let Some(tag) = id3::parse(&bytes.subarray(0, size as u32).to_vec()) {
+                metadata.apply_tag(tag, &self.events)?;
+            }
+        }
+
+        JsFuture::from(self.context.decode_audio_data(data)?).await?.dyn_into()
+    }
+
+    async fn play_audio_buffer(&mut self, audio_buffer: &web_sys::AudioBuffer) -> Result<usize, JsValue> {
+        self.events
+            .emit1(PlayerEvent::TrackLoaded, &audio_buffer.duration().into());
+        self.set_waveform(Some(Rc::new(Waveform::from_buffer(audio_buffer)?)));
+        JsFuture::from(self.context.resume()?).await?;
+
+        let bus = self.context.create_gain()?;
+        let id = self.add_source(bus.clone(), true)?;
+        let mut track = BufferTrack::new(
+            &self.context,
+            audio_buffer,
+            bus,
+            &self.looping,
+            &self.events,
+            self.mixer.ended_flag(id),
+        );
+        track.start(0.0)?;
+        *self.buffer_track.borrow_mut() = Some(track);
+
+        self.is_playing = true;
+        self.playback_mode = Some(PlaybackMode::DecodeAudioData);
+        self.events.emit(PlayerEvent::Play);
+
+        Ok(id)
+    }
+
+    // A preload fetched with other headers or credentials may not be what the caller
+    // is allowed to hear, so it is dropped rather than played.
+    fn take_preloaded(&mut self, url: &str, request: &RequestOptions) -> Option<Preloaded> {
+        match self.preloaded {
+            Some(ref preloaded) if preloaded.url == url && preloaded.request == *request => {
+                self.preloaded.take()
+            }
+            Some(ref preloaded) if preloaded.url == url => {
+                self.clear_preload();
+                None
+            }
+            _ => None,
+        }
+    }
+
+    async fn play_preloaded(&mut self, preloaded: Preloaded) -> Result<usize, JsValue> {
+        log(&format!("Starting preloaded {}", preloaded.url));
+
+        self.metadata.clear();
+        self.metadata = preloaded.metadata;
+        self.metadata.activate(&self.events)?;
+        self.set_show_album_art(self.show_album_art);
+
+        match preloaded.standby {
+            Standby::Element(element, bus, mode) => {
+                self.start_audio_element(&element, bus, mode).await
+            }
+            Standby::Buffer(audio_buffer) => self.play_audio_buffer(&audio_buffer).await,
+        }
+    }
+
+    fn set_waveform(&mut self, waveform: Option<Rc<Waveform>>) {
+        let ready = waveform.is_some();
+        self.waveform = waveform;
+        for instance in self.instances.borrow_mut().iter_mut() {
+            instance.set_waveform(self.waveform.clone());
+        }
+        if ready {
+            self.events.emit(PlayerEvent::WaveformReady);
+        }
+    }
+}
+
+struct StyleTransition {
+    from: Style,
+    from_surface: Surface,
+    to_surface: Surface,
+    started_at: f64,
+    duration: f64,
+}
+
+struct AudioVisualizerInstance {
+    style: Style,
+    transition: Option<StyleTransition>,
+    base_surface: Option<Surface>,
+    layers: Vec<Layer>,
+    next_layer_id: usize,
+    canvas: HtmlCanvasElement,
+    ctx: CanvasRenderingContext2d,
+    css_width: u32,
+    css_height: u32,
+    pixel_ratio: f64,
+    size_override: Option<(u32, u32)>,
+    reduced_motion: bool,
+    quality: f64,
+    center_image: Option<web_sys::HtmlImageElement>,
+    logo: Option<web_sys::HtmlImageElement>,
+    background: Option<Background>,
+    text: Option<TextOverlay>,
+    waveform: Option<Rc<Waveform>>,
+    source: Option<usize>,
+    pan: f64,
+    seed: Option<u64>,
+}
+
+impl AudioVisualizerInstance {
+    fn new(canvas: HtmlCanvasElement, style_type: StyleType) -> Result<Self, JsValue> {
+        let ctx = canvas
+            .get_context("2d")?
+            .ok_or_else(|| JsValue::from_str("Failed to get 2D context"))?
+            .dyn_into::<CanvasRenderingContext2d>()?;
+
+        let style = Style::new(style_type, canvas.clone())?;
+
+        let css_width = canvas.width();
+        let css_height = canvas.height();
+
+        Ok(AudioVisualizerInstance {
+            style,
+            transition: None,
+            base_surface: None,
+            layers: Vec::new(),
+            next_layer_id: 0,
+            canvas,
+            ctx,
+            css_width,
+            css_height,
+            pixel_ratio: 1.0,
+            size_override: None,
+            reduced_motion: false,
+            quality: 1.0,
+            center_image: None,
+            logo: None,
+            background: None,
+            text: None,
+            waveform: None,
+            source: None,
+            pan: 0.0,
+            seed: None,
+        })
+    }
+
+    fn resize(&mut self) -> Result<(), JsValue> {
+        let pixel_ratio = web_sys::window()
+            .map(|window| window.device_pixel_ratio())
+            .filter(|ratio| *ratio > 0.0)
+            .unwrap_or(1.0);
+
+        let (css_width, css_height) = match self.size_override {
+            Some(size) => size,
+            None => match (self.canvas.client_width(), self.canvas.client_height()) {
+                (width, height) if width > 0 && height > 0 => (width as u32, height as u32),
+                _ => (self.css_width, self.css_height),
+            },
+        };
+
+        let backing_width = (css_width as f64 * pixel_ratio).round() as u32;
+        let backing_height = (css_height as f64 * pixel_ratio).round() as u32;
+        if backing_width == self.canvas.width()
+            && backing_height == self.canvas.height()
+            && pixel_ratio == self.pixel_ratio
+        {
+            return Ok(());
+        }
+
+        // Without a CSS size the layout follows the width/height attributes,
+        // so pin it before enlarging the backing store.
+        let style = self.canvas.style();
+        if self.size_override.is_none()
+            && style.get_property_value("width")?.is_empty()
+            && css_width == self.canvas.width()
+            && css_height == self.canvas.height()
+        {
+            style.set_property("width", &format!("{}px", css_width))?;
+            style.set_property("height", &format!("{}px", css_height))?;
+        }
+
+        self.canvas.set_width(backing_width);
+        self.canvas.set_height(backing_height);
+        self.ctx
+            .set_transform(pixel_ratio, 0.0, 0.0, pixel_ratio, 0.0, 0.0)?;
+
+        self.css_width = css_width;
+        self.css_height = css_height;
+        self.pixel_ratio = pixel_ratio;
+
+        if let Some(ref transition) = self.transition {
+            transition.from_surface.resize(backing_width, backing_height, pixel_ratio)?;
+            transition.to_surface.resize(backing_width, backing_height, pixel_ratio)?;
+        }
+        if let Some(ref surface) = self.base_surface {
+            surface.resize(backing_width, backing_height, pixel_ratio)?;
+        }
+        for layer in self.layers.iter() {
+            layer.surface.resize(backing_width, backing_height, pixel_ratio)?;
+        }
+        self.for_each_style(|style| style.resize(css_width, css_height));
+
+        Ok(())
+    }
+
+    fn set_style(&mut self, style_type: StyleType, transition_ms: f64) -> Result<(), JsValue> {
+        if self.transition.is_none() && self.style.style_type() == style_type {
+            return Ok(());
+        }
+
+        if transition_ms <= 0.0 {
+            let mut style = Style::new(style_type, self.canvas.clone())?;
+            self.configure(&mut style);
+            style.set_context(self.base_target().1);
+            self.style = style;
+            self.transition = None;
+            return Ok(());
+        }
+
+        let (width, height) = (self.canvas.width(), self.canvas.height());
+        let from_surface = match self.transition.take() {
+            Some(transition) => transition.to_surface,
+            None => {
+                let surface = Surface::new(width, height, self.pixel_ratio)?;
+                surface.copy_from(&self.base_target().0)?;
+                surface
+            }
+        };
+        let to_surface = Surface::new(width, height, self.pixel_ratio)?;
+
+        let mut style = Style::new(style_type, to_surface.canvas().clone())?;
+        self.configure(&mut style);
+        let mut from = std::mem::replace(&mut self.style, style);
+        from.set_context(from_surface.context().clone());
+
+        self.transition = Some(StyleTransition {
+            from,
+            from_surface,
+            to_surface,
+            started_at: now_ms(),
+            duration: transition_ms,
+        });
+        Ok(())
+    }
+
+    fn add_layer(
+        &mut self,
+        style_type: StyleType,
+        blend: BlendMode,
+        opacity: f64,
+    ) -> Result<usize, JsValue> {
+        let (width, height) = (self.canvas.width(), self.canvas.height());
+        self.ensure_base_surface()?;
+
+        let surface = Surface::new(width, height, self.pixel_ratio)?;
+        let mut style = Style::new(style_type, surface.canvas().clone())?;
+        self.configure(&mut style);
+        style.set_background(Some(Background::fade(LAYER_FADE_ALPHA)));
+        let id = self.next_layer_id;
+        self.next_layer_id += 1;
+        self.layers.push(Layer {
+            id,
+            style,
+            surface,
+            blend,
+            opacity: opacity.clamp(0.0, 1.0),
+        });
+        Ok(id)
+    }
+
+    fn remove_layer(&mut self, id: usize) -> Result<(), JsValue> {
+        let index = self
+            .layers
+            .iter()
+            .position(|layer| layer.id == id)
+            .ok_or_else(|| JsValue::from_str("No layer with that id"))?;
+        self.layers.remove(index);
+        self.release_base_surface()
+    }
+
+    fn set_text(&mut self, text: Option<TextOverlay>) -> Result<(), JsValue> {
+        self.text = text;
+        if self.text.is_some() {
+            self.ensure_base_surface()
+        } else {
+            self.release_base_surface()
+        }
+    }
+
+    fn ensure_base_surface(&mut self) -> Result<(), JsValue> {
+        if self.base_surface.is_none() {
+            let (width, height) = (self.canvas.width(), self.canvas.height());
+            let surface = Surface::new(width, height, self.pixel_ratio)?;
+            surface.copy_from(&self.canvas)?;
+            if self.transition.is_none() {
+                self.style.set_context(surface.context().clone());
+            }
+            self.base_surface = Some(surface);
+        }
+        Ok(())
+    }
+
+    fn release_base_surface(&mut self) -> Result<(), JsValue> {
+        if !self.layers.is_empty() || self.text.is_some() {
+            return Ok(());
+        }
+        if let Some(surface) = self.base_surface.take() {
+            clear_canvas(&self.canvas, &self.ctx);
+            surface.composite(&self.ctx, 1.0, BlendMode::Normal.operation())?;
+        }
+        if self.transition.is_none() {
+            self.style.set_context(self.ctx.clone());
+        }
+        Ok(())
+    }
+
+    fn layer_mut(&mut self, id: usize) -> Result<&mut Layer, JsValue> {
+        self.layers
+            .iter_mut()
+            .find(|layer| layer.id == id)
+            .ok_or_else(|| JsValue::from_str("No layer with that id"))
+    }
+
+    fn base_target(&self) -> (HtmlCanvasElement, CanvasRenderingContext2d) {
+        match self.base_surface {
+            Some(ref surface) => (surface.canvas().clone(), surface.context().clone()),
+            None => (self.canvas.clone(), self.ctx.clone()),
+        }
+    }
+
+    fn configure(&self, style: &mut Style) {
+        style.resize(self.css_width, self.css_height);
+        style.set_reduced_motion(self.reduced_motion);
+        style.set_quality(self.quality);
+        style.set_center_image(self.displayed_image());
+        style.set_background(self.background.clone());
+        style.set_waveform(self.waveform.clone());
+        style.set_pan(self.pan);
+        if let Some(seed) = self.seed {
+            style.set_seed(seed);
+        }
+    }
+
+    fn set_background(&mut self, background: Option<Background>) {
+        self.style.set_background(background.clone());
+        if let Some(ref mut transition) = self.transition {
+            transition.from.set_background(background.clone());
+        }
+        self.background = background;
+    }
+
+    fn for_each_style(&mut self, mut f: impl FnMut(&mut Style)) {
+        f(&mut self.style);
+        if let Some(ref mut transition) = self.transition {
+            f(&mut transition.from);
+        }
+        for layer in self.layers.iter_mut() {
+            f(&mut layer.style);
+        }
+    }
+
+    fn draw(&mut self, audio_data: &[u8], camera: Option<CameraTransform>) {
+        let (base_canvas, base_ctx) = self.base_target();
+        let (ratio, width, height) = (self.pixel_ratio, self.css_width, self.css_height);
+
+        match self.transition {
+            None => {
+                set_camera(&base_ctx, ratio, width, height, camera);
+                self.style.draw(audio_data);
+                if camera.is_some() {
+                    set_camera(&base_ctx, ratio, width, height, None);
+                }
+            }
+            Some(ref mut transition) => {
+                let surfaces = [
+                    (&mut transition.from, &transition.from_surface),
+                    (&mut self.style, &transition.to_surface),
+                ];
+                for (style, surface) in surfaces {
+                    set_camera(surface.context(), ratio, width, height, camera);
+                    style.draw(audio_data);
+                }
+
+                let elapsed = now_ms() - transition.started_at;
+                let progress = (elapsed / transition.duration).clamp(0.0, 1.0);
+                let eased = progress * progress * (3.0 - 2.0 * progress);
+
+                clear_canvas(&base_canvas, &base_ctx);
+                let _ = transition.from_surface.composite(&base_ctx, 1.0 - eased, "source-over");
+                let _ = transition.to_surface.composite(&base_ctx, eased, "lighter");
+
+                if progress >= 1.0 {
+                    self.transition = None;
+                    self.style.set_context(base_ctx);
+                }
+            }
+        }
+
+        if let Some(ref base) = self.base_surface {
+            clear_canvas(&self.canvas, &self.ctx);
+            let _ = base.composite(&self.ctx, 1.0, BlendMode::Normal.operation());
+            for layer in self.layers.iter_mut() {
+                set_camera(layer.surface.context(), ratio, width, height, camera);
+                layer.style.draw(audio_data);
+                let _ = layer
+                    .surface
+                    .composite(&self.ctx, layer.opacity, layer.blend.operation());
+            }
+            if let Some(ref mut text) = self.text {
+                text.draw(&self.ctx, width, height, audio_data, self.reduced_motion);
+            }
+        }
+    }
+
+    fn set_reduced_motion(&mut self, enabled: bool) {
+        self.reduced_motion = enabled;
+        self.for_each_style(|style| style.set_reduced_motion(enabled));
+    }
+
+    fn trigger_onset(&mut self) {
+        self.for_each_style(|style| style.trigger_onset());
+    }
+
+    fn set_features(&mut self, features: &SpectralFeatures) {
+        self.for_each_style(|style| style.set_features(features));
+    }
+
+    fn set_key_hue(&mut self, hue: Option<f64>) {
+        self.for_each_style(|style| style.set_key_hue(hue));
+    }
+
+    fn set_waveform(&mut self, waveform: Option<Rc<Waveform>>) {
+        self.waveform = waveform.clone();
+        self.for_each_style(|style| style.set_waveform(waveform.clone()));
+    }
+
+    fn set_pan(&mut self, pan: f64) {
+        self.pan = pan;
+        self.for_each_style(|style| style.set_pan(pan));
+    }
+
+    fn set_progress(&mut self, progress: f64) {
+        self.for_each_style(|style| style.set_progress(progress));
+    }
+
+    fn set_center_image(&mut self, image: Option<web_sys::HtmlImageElement>) {
+        self.center_image = image;
+        self.apply_center_image();
+    }
+
+    fn set_logo(&mut self, logo: Option<web_sys::HtmlImageElement>) {
+        self.logo = logo;
+        self.apply_center_image();
+    }
+
+    fn displayed_image(&self) -> Option<web_sys::HtmlImageElement> {
+        self.logo.clone().or_else(|| self.center_image.clone())
+    }
+
+    fn apply_center_image(&mut self) {
+        let image = self.displayed_image();
+        self.for_each_style(|style| style.set_center_image(image.clone()));
+    }
+
+    fn set_symmetry(&mut self, symmetry: Symmetry, rotation_speed: f64) -> bool {
+        let mut applied = false;
+        self.for_each_style(|style| applied |= style.set_symmetry(symmetry, rotation_speed));
+        applied
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+        self.for_each_style(|style| style.set_seed(seed));
+    }
+
+    fn set_particle_config(&mut self, config: &ParticleConfig) -> bool {
+        let mut applied = false;
+        self.for_each_style(|style| applied |= style.set_particle_config(config));
+        applied
+    }
+
+    fn preset(&self) -> InstanceOptions {
+        InstanceOptions {
+            style: self.style.style_type(),
+            source: None,
+            background: self.background.as_ref().map(|background| background.kind().clone()),
+            effects: self.style.options(),
+            layers: self
+                .layers
+                .iter()
+                .map(|layer| LayerOptions {
+                    style: layer.style.style_type(),
+                    blend: layer.blend,
+                    opacity: layer.opacity,
+                    effects: layer.style.options(),
+                })
+                .collect(),
+        }
+    }
+
+    fn apply_preset(&mut self, options: &InstanceOptions) -> Result<(), JsValue> {
+        self.set_style(options.style, 0.0)?;
+        self.set_background(options.background.clone().map(Background::from));
+        self.style.apply_options(&options.effects);
+        self.layers.clear();
+        for layer in &options.layers {
+            let id = self.add_layer(layer.style, layer.blend, layer.opacity)?;
+            self.layer_mut(id)?.style.apply_options(&layer.effects);
+        }
+        self.release_base_surface()?;
+        Ok(())
+    }
+
+    fn set_quality(&mut self, quality: f64) {
+        self.quality = quality;
+        self.for_each_style(|style| style.set_quality(quality));
+    }
+
+    fn clear_canvas(&self) {
+        clear_canvas(&self.canvas, &self.ctx);
+    }
+}
+
+fn set_camera(
+    ctx: &CanvasRenderingContext2d,
+    ratio: f64,
+    css_width: u32,
+    css_height: u32,
+    camera: Option<CameraTransform>,
+) {
+    let _ = match camera {
+        Some(camera) => {
+            let scale = ratio * camera.scale;
+            let center_x = css_width as f64 / 2.0;
+            let center_y = css_height as f64 / 2.0;
+            ctx.set_transform(
+                scale,
+                0.0,
+                0.0,
+                scale,
+                ratio * (center_x * (1.0 - camera.scale) + camera.offset_x),
+                ratio * (center_y * (1.0 - camera.scale) + camera.offset_y),
+            )
+        }
+        None => ctx.set_transform(ratio, 0.0, 0.0, ratio, 0.0, 0.0),
+    };
+}
+
+fn clear_canvas(canvas: &HtmlCanvasElement, ctx: &CanvasRenderingContext2d) {
+    ctx.save();
+    let _ = ctx.set_transform(1.0, 0.0, 0.0, 1.0, 0.0, 0.0);
+    ctx.clear_rect(0.0, 0.0, canvas.width() as f64, canvas.height() as f64);
+    ctx.restore();
+}
+
+fn log(s: &str) {
+    web_sys::console::log_1(&JsValue::from_str(s));
+}
+
+fn resolve_url(path: &str) -> String {
+    if !path.starts_with("http") {
+        format!("http://127.0.0.1:3000{}", path)
+    } else {
+        path.to_string()
+    }
+}
+
+async fn fetch_array_buffer(
+    url: &str,
+    request: &RequestOptions,
+) -> Result<js_sys::ArrayBuffer, JsValue> {
+    let window = web_sys::window().ok_or_else(|| JsValue::from_str("No window found"))?;
+    let response = request.fetch(&window, url, &[]).await?;
+
+    if !response.ok() {
+        return Err(JsValue::from_str("Failed to fetch audio file"));
+    }
+
+    JsFuture::from(response.array_buffer()?).await?.dyn_into()
+}
+
+fn now_ms() -> f64 {
+    web_sys::window()
+        .and_then(|window| window.performance())
+        .map(|performance| performance.now())
+        .unwrap_or_else(js_sys::Date::now)
+}
+
+fn document() -> Result<web_sys::Document, JsValue> {
+    web_sys::window()
+        .and_then(|window| window.document())
+        .ok_or_else(|| JsValue::from_str("No document found"))
+}
+
+fn apply_fullscreen(instances: &RefCell<Vec<AudioVisualizerInstance>>) -> Result<(), JsValue> {
+    let window = web_sys::window().ok_or_else(|| JsValue::from_str("No window found"))?;
+    let screen = window.screen()?;
+    let screen_size = (screen.width()? as u32, screen.height()? as u32);
+    let fullscreen_element = document()?.fullscreen_element();
+
+    for instance in instances.borrow_mut().iter_mut() {
+        let is_fullscreen = fullscreen_element
+            .as_ref()
+            .map(|element| element == instance.canvas.unchecked_ref::<web_sys::Element>())
+            .unwrap_or(false);
+        instance.size_override = if is_fullscreen { Some(screen_size) } else { None };
+        instance.resize()?;
+    }
+
+    Ok(())
+}
+
+fn prefers_reduced_motion() -> bool {
+    web_sys::window()
+        .and_then(|window| window.match_media("(prefers-reduced-motion: reduce)").ok())
+        .flatten()
+        .map(|query| query.matches())
+        .unwrap_or(false)
+}
+
+fn has_media_source() -> bool {
+    js_sys::Reflect::has(&js_sys::global(), &JsValue::from_str("MediaSource")).unwrap_or(false)
+}
+
+fn media_source_supported(mime_type: &str) -> bool {
+    has_media_source() && MediaSource::is_type_supported(mime_type)
+}
+
+fn pause_playback(
+    audio_element: &Option<HtmlMediaElement>,
+    context: &AudioContext,
+) -> Result<(), JsValue> {
+    match audio_element {
+        Some(element) => element.pause(),
+        None => context.suspend().map(|_| ()),
+    }
+}
+
+fn resume_playback(
+    audio_element: &Option<HtmlMediaElement>,
+    context: &AudioContext,
+) -> Result<(), JsValue> {
+    match audio_element {
+        Some(element) => element.play().map(|_| ()),
+        None => context.resume().map(|_| ()),
+    }
+}
+
+fn seek_playback(
+    audio_element: &Option<HtmlMediaElement>,
+    buffer_track: &mut Option<BufferTrack>,
+    time: f64,
+) -> Result<(), JsValue> {
+    match (audio_element, buffer_track) {
+        (Some(element), _) => {
+            element.set_current_time(time);
+            Ok(())
+        }
+        (None, Some(track)) => track.start(time),
+        (None, None) => Err(JsValue::from_str("Seeking is not supported for this source")),
+    }
+}
+
+fn playback_position(
+    audio_element: &Option<HtmlMediaElement>,
+    buffer_track: &Option<BufferTrack>,
+) -> Option<f64> {
+    match (audio_element, buffer_track) {
+        (Some(element), _) => Some(element.current_time()),
+        (None, Some(track)) => Some(track.current_time()),
+        (None, None) => None,
+    }
+}
+
+fn wrap_ab_loop(element: &HtmlMediaElement, looping: &SharedLoop) {
+    if let Some(start) = looping.wrap(element.current_time()) {
+        element.set_current_time(start);
+    }
+}
+
+fn ended_callback(
+    events: &EventCallbacks,
+    looping: &SharedLoop,
+    element: Option<HtmlMediaElement>,
+    ended: Option<Rc<Cell<bool>>>,
+) -> Closure<dyn FnMut()> {
+    let looping = looping.clone();
+    events.listener(move |events| {
+        if let (Some(element), Some(start)) = (element.as_ref(), looping.restart_at()) {
+            element.set_current_time(start);
+            let _ = element.play();
+            return;
+        }
+
+        log("Audio playback ended");
+        if let Some(ref ended) = ended {
+            ended.set(true);
+        }
+        events.emit(PlayerEvent::Ended);
+        looping.advance();
+    })
 }
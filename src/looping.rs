@@ -0,0 +1,126 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum LoopMode {
+    #[default]
+    None,
+    Track,
+    // Steps through the tracks given to `set_playlist`, wrapping to the first.
+    Playlist,
+}
+
+#[derive(Default)]
+struct LoopState {
+    mode: LoopMode,
+    ab: Option<(f64, f64)>,
+    next_track: Option<js_sys::Function>,
+    playlist_length: usize,
+    position: usize,
+}
+
+#[derive(Clone, Default)]
+pub struct SharedLoop {
+    inner: Rc<RefCell<LoopState>>,
+}
+
+impl SharedLoop {
+    pub fn mode(&self) -> LoopMode {
+        self.inner.borrow().mode
+    }
+
+    pub fn set_mode(&self, mode: LoopMode) {
+        self.inner.borrow_mut().mode = mode;
+    }
+
+    pub fn ab(&self) -> Option<(f64, f64)> {
+        self.inner.borrow().ab
+    }
+
+    pub fn set_ab(&self, ab: Option<(f64, f64)>) {
+        self.inner.borrow_mut().ab = ab;
+    }
+
+    pub fn set_next_track(&self, callback: Option<js_sys::Function>) {
+        self.inner.borrow_mut().next_track = callback;
+    }
+
+    pub fn set_playlist(&self, length: usize, position: usize) {
+        let mut state = self.inner.borrow_mut();
+        state.playlist_length = length;
+        state.position = position;
+    }
+
+    pub fn has_playlist(&self) -> bool {
+        self.inner.borrow().playlist_length > 0
+    }
+
+    pub fn position(&self) -> usize {
+        self.inner.borrow().position
+    }
+
+    pub fn restart_at(&self) -> Option<f64> {
+        let state = self.inner.borrow();
+        match (state.ab, state.mode) {
+            (Some((start, _)), _) => Some(start),
+            (None, LoopMode::Track) => Some(0.0),
+            _ => None,
+        }
+    }
+
+    pub fn wrap(&self, position: f64) -> Option<f64> {
+        let (start, end) = self.ab()?;
+        (position >= end).then_some(start)
+    }
+
+    pub fn fold(&self, position: f64, duration: f64) -> f64 {
+        let Some(start) = self.restart_at() else {
+            return position;
+        };
+        let end = self.ab().map_or(duration, |(_, end)| end.min(duration));
+        if position < end || end <= start {
+            return position;
+        }
+        start + (position - end) % (end - start)
+    }
+
+    pub fn advance(&self) {
+        let (next_track, position) = {
+            let mut state = self.inner.borrow_mut();
+            if state.mode != LoopMode::Playlist || state.playlist_length == 0 {
+                return;
+            }
+            state.position = (state.position + 1) % state.playlist_length;
+            (state.next_track.clone(), state.position)
+        };
+        if let Some(callback) = next_track {
+            let _ = callback.call1(&JsValue::NULL, &position.into());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn playlist_wraps_to_first_track() {
+        let looping = SharedLoop::default();
+        looping.set_mode(LoopMode::Playlist);
+        looping.set_playlist(3, 1);
+        looping.advance();
+        assert_eq!(looping.position(), 2);
+        looping.advance();
+        assert_eq!(looping.position(), 0);
+    }
+
+    #[test]
+    fn other_modes_keep_position() {
+        let looping = SharedLoop::default();
+        looping.set_playlist(3, 1);
+        looping.advance();
+        assert_eq!(looping.position(), 1);
+    }
+}
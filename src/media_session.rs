@@ -0,0 +1,173 @@
+use crate::buffer_track::BufferTrack;
+use crate::{pause_playback, playback_position, resume_playback, seek_playback};
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{AudioContext, HtmlMediaElement};
+
+const DEFAULT_SEEK_OFFSET: f64 = 10.0;
+
+#[wasm_bindgen]
+extern "C" {
+    type MediaSession;
+
+    #[wasm_bindgen(method, setter)]
+    fn set_metadata(this: &MediaSession, metadata: &MediaMetadata);
+
+    #[wasm_bindgen(method, setter, js_name = playbackState)]
+    fn set_playback_state(this: &MediaSession, state: &str);
+
+    #[wasm_bindgen(method, catch, js_name = setActionHandler)]
+    fn set_action_handler(
+        this: &MediaSession,
+        action: &str,
+        handler: Option<&js_sys::Function>,
+    ) -> Result<(), JsValue>;
+
+    type MediaMetadata;
+
+    #[wasm_bindgen(constructor, catch)]
+    fn new(init: &JsValue) -> Result<MediaMetadata, JsValue>;
+}
+
+pub struct MediaSessionHandlers {
+    _closures: Vec<Closure<dyn FnMut(JsValue)>>,
+}
+
+fn media_session() -> Option<MediaSession> {
+    let navigator = web_sys::window()?.navigator();
+    js_sys::Reflect::get(&navigator, &"mediaSession".into())
+        .ok()
+        .filter(|session| !session.is_undefined() && !session.is_null())
+        .map(|session| session.unchecked_into())
+}
+
+pub fn set_metadata(title: &str, artist: &str, artwork_url: Option<String>) -> Result<(), JsValue> {
+    let session = match media_session() {
+        Some(session) => session,
+        None => return Ok(()),
+    };
+
+    let init = js_sys::Object::new();
+    js_sys::Reflect::set(&init, &"title".into(), &JsValue::from_str(title))?;
+    js_sys::Reflect::set(&init, &"artist".into(), &JsValue::from_str(artist))?;
+    if let Some(url) = artwork_url {
+        let image = js_sys::Object::new();
+        js_sys::Reflect::set(&image, &"src".into(), &JsValue::from_str(&url))?;
+        js_sys::Reflect::set(&init, &"artwork".into(), &js_sys::Array::of1(&image))?;
+    }
+
+    session.set_metadata(&MediaMetadata::new(&init)?);
+    Ok(())
+}
+
+pub fn set_playback_state(playing: bool) {
+    if let Some(session) = media_session() {
+        session.set_playback_state(if playing { "playing" } else { "paused" });
+    }
+}
+
+pub fn register_handlers(
+    audio_element: Rc<RefCell<Option<HtmlMediaElement>>>,
+    buffer_track: Rc<RefCell<Option<BufferTrack>>>,
+    context: AudioContext,
+    on_next_track: Option<js_sys::Function>,
+    on_previous_track: Option<js_sys::Function>,
+) -> Option<MediaSessionHandlers> {
+    let session = media_session()?;
+    let mut closures = Vec::new();
+
+    let mut register = |action: &str, handler: Box<dyn FnMut(JsValue)>| {
+        let closure = Closure::wrap(handler);
+        if session
+            .set_action_handler(action, Some(closure.as_ref().unchecked_ref()))
+            .is_ok()
+        {
+            closures.push(closure);
+        }
+    };
+
+    {
+        let audio_element = audio_element.clone();
+        let context = context.clone();
+        register(
+            "play",
+            Box::new(move |_| {
+                if resume_playback(&audio_element.borrow(), &context).is_ok() {
+                    set_playback_state(true);
+                }
+            }),
+        );
+    }
+
+    {
+        let audio_element = audio_element.clone();
+        register(
+            "pause",
+            Box::new(move |_| {
+                if pause_playback(&audio_element.borrow(), &context).is_ok() {
+                    set_playback_state(false);
+                }
+            }),
+        );
+    }
+
+    {
+        let audio_element = audio_element.clone();
+        let buffer_track = buffer_track.clone();
+        register(
+            "seekto",
+            Box::new(move |details| {
+                if let Some(time) = number_field(&details, "seekTime").filter(|time| *time >= 0.0) {
+                    let _ = seek_playback(
+                        &audio_element.borrow(),
+                        &mut buffer_track.borrow_mut(),
+                        time,
+                    );
+                }
+            }),
+        );
+    }
+
+    for (action, direction) in [("seekbackward", -1.0), ("seekforward", 1.0)] {
+        let audio_element = audio_element.clone();
+        let buffer_track = buffer_track.clone();
+        register(
+            action,
+            Box::new(move |details| {
+                let position = playback_position(&audio_element.borrow(), &buffer_track.borrow());
+                if let Some(position) = position {
+                    let offset = number_field(&details, "seekOffset").unwrap_or(DEFAULT_SEEK_OFFSET);
+                    let _ = seek_playback(
+                        &audio_element.borrow(),
+                        &mut buffer_track.borrow_mut(),
+                        (position + direction * offset).max(0.0),
+                    );
+                }
+            }),
+        );
+    }
+
+    for (action, callback) in [("nexttrack", on_next_track), ("previoustrack", on_previous_track)] {
+        match callback {
+            Some(callback) => register(
+                action,
+                Box::new(move |_| {
+                    let _ = callback.call0(&JsValue::NULL);
+                }),
+            ),
+            None => {
+                let _ = session.set_action_handler(action, None);
+            }
+        }
+    }
+
+    Some(MediaSessionHandlers { _closures: closures })
+}
+
+fn number_field(details: &JsValue, name: &str) -> Option<f64> {
+    js_sys::Reflect::get(details, &name.into())
+        .ok()
+        .and_then(|value| value.as_f64())
+}
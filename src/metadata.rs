@@ -0,0 +1,145 @@
+use crate::events::{EventCallbacks, PlayerEvent};
+use crate::id3::Id3Tag;
+use crate::media_session;
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{Blob, BlobPropertyBag, HtmlImageElement, Url};
+
+#[derive(Default)]
+pub struct TrackMetadata {
+    title: Option<String>,
+    artist: Option<String>,
+    album: Option<String>,
+    artwork_url: Option<String>,
+    artwork: Option<HtmlImageElement>,
+    artwork_changed: bool,
+    standby: bool,
+}
+
+#[derive(Clone, Default)]
+pub struct SharedMetadata {
+    inner: Rc<RefCell<TrackMetadata>>,
+}
+
+impl SharedMetadata {
+    pub fn standby() -> SharedMetadata {
+        let metadata = SharedMetadata::default();
+        metadata.inner.borrow_mut().standby = true;
+        metadata
+    }
+
+    pub fn activate(&self, events: &EventCallbacks) -> Result<(), JsValue> {
+        self.inner.borrow_mut().standby = false;
+        self.notify(events)
+    }
+
+    pub fn to_js(&self) -> JsValue {
+        let metadata = self.inner.borrow();
+        let object = js_sys::Object::new();
+        let fields = [
+            ("title", &metadata.title),
+            ("artist", &metadata.artist),
+            ("album", &metadata.album),
+            ("artwork_url", &metadata.artwork_url),
+        ];
+        for (key, value) in fields {
+            let value = value.as_deref().map(JsValue::from_str).unwrap_or(JsValue::NULL);
+            let _ = js_sys::Reflect::set(&object, &key.into(), &value);
+        }
+        object.into()
+    }
+
+    pub fn apply_tag(&self, tag: Id3Tag, events: &EventCallbacks) -> Result<(), JsValue> {
+        self.clear();
+        {
+            let mut metadata = self.inner.borrow_mut();
+            metadata.title = tag.title;
+            metadata.artist = tag.artist;
+            metadata.album = tag.album;
+        }
+
+        if let Some(picture) = tag.picture {
+            let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(picture.data.as_slice()));
+            let options = BlobPropertyBag::new();
+            options.set_type(&picture.mime_type);
+            let blob = Blob::new_with_u8_array_sequence_and_options(&parts, &options)?;
+            let url = Url::create_object_url_with_blob(&blob)?;
+            self.load_artwork(&url)?;
+            self.inner.borrow_mut().artwork_url = Some(url);
+        }
+
+        self.notify(events)
+    }
+
+    pub fn set_title(&self, title: &str, events: &EventCallbacks) -> Result<(), JsValue> {
+        self.inner.borrow_mut().title = Some(title.to_string());
+        self.notify(events)
+    }
+
+    pub fn clear(&self) {
+        let mut metadata = self.inner.borrow_mut();
+        if let Some(url) = metadata.artwork_url.take() {
+            let _ = Url::revoke_object_url(&url);
+        }
+        let had_artwork = metadata.artwork.is_some();
+        *metadata = TrackMetadata {
+            artwork_changed: had_artwork,
+            standby: metadata.standby,
+            ..TrackMetadata::default()
+        };
+    }
+
+    pub fn take_artwork_change(&self) -> Option<Option<HtmlImageElement>> {
+        let mut metadata = self.inner.borrow_mut();
+        if metadata.artwork_changed {
+            metadata.artwork_changed = false;
+            Some(metadata.artwork.clone())
+        } else {
+            None
+        }
+    }
+
+    pub fn artwork(&self) -> Option<HtmlImageElement> {
+        self.inner.borrow().artwork.clone()
+    }
+
+    fn load_artwork(&self, url: &str) -> Result<(), JsValue> {
+        let image = HtmlImageElement::new()?;
+        let on_load = {
+            let inner = self.inner.clone();
+            let image = image.clone();
+            let url = url.to_string();
+            Closure::once_into_js(move || {
+                let mut metadata = inner.borrow_mut();
+                if metadata.artwork_url.as_deref() == Some(url.as_str()) {
+                    metadata.artwork = Some(image);
+                    metadata.artwork_changed = true;
+                }
+            })
+        };
+        image.set_onload(Some(on_load.unchecked_ref()));
+        image.set_src(url);
+        Ok(())
+    }
+
+    fn notify(&self, events: &EventCallbacks) -> Result<(), JsValue> {
+        {
+            let metadata = self.inner.borrow();
+            // A preloaded track must not replace what the current one is showing.
+            if metadata.standby {
+                return Ok(());
+            }
+            if metadata.title.is_some() || metadata.artist.is_some() {
+                media_session::set_metadata(
+                    metadata.title.as_deref().unwrap_or_default(),
+                    metadata.artist.as_deref().unwrap_or_default(),
+                    metadata.artwork_url.clone(),
+                )?;
+            }
+        }
+        events.emit1(PlayerEvent::Metadata, &self.to_js());
+        Ok(())
+    }
+}
@@ -0,0 +1,154 @@
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use web_sys::{AnalyserNode, AudioContext, AudioNode, GainNode, StereoPannerNode};
+
+struct MixerSource {
+    bus: GainNode,
+    analyser: Option<AnalyserNode>,
+    ended: Rc<Cell<bool>>,
+}
+
+impl MixerSource {
+    fn disconnect(&self) {
+        let _ = AudioNode::disconnect(&self.bus);
+        if let Some(ref analyser) = self.analyser {
+            let _ = AudioNode::disconnect(analyser);
+        }
+    }
+}
+
+pub struct Mixer {
+    mix: GainNode,
+    level: GainNode,
+    panner: StereoPannerNode,
+    output: GainNode,
+    // Ids are never reused, so a stale id can't end up addressing a newer source.
+    sources: HashMap<usize, MixerSource>,
+    next_id: usize,
+}
+
+impl Mixer {
+    pub fn new(context: &AudioContext, analyser: &AnalyserNode) -> Result<Mixer, JsValue> {
+        let mix = context.create_gain()?;
+        mix.connect_with_audio_node(analyser)?;
+        let level = context.create_gain()?;
+        let panner = context.create_stereo_panner()?;
+        let output = context.create_gain()?;
+        level.connect_with_audio_node(&panner)?;
+        panner.connect_with_audio_node(&output)?;
+        Ok(Mixer {
+            mix,
+            level,
+            panner,
+            output,
+            sources: HashMap::new(),
+            next_id: 0,
+        })
+    }
+
+    pub fn mix(&self) -> &GainNode {
+        &self.mix
+    }
+
+    pub fn output(&self) -> &GainNode {
+        &self.output
+    }
+
+    pub fn set_muted(&self, muted: bool) {
+        self.output.gain().set_value(if muted { 0.0 } else { 1.0 });
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.output.gain().value() == 0.0
+    }
+
+    pub fn set_pan(&self, pan: f32) {
+        self.panner.pan().set_value(pan);
+    }
+
+    pub fn add(&mut self, bus: GainNode, audible: bool) -> Result<usize, JsValue> {
+        bus.connect_with_audio_node(&self.mix)?;
+        if audible {
+            bus.connect_with_audio_node(&self.level)?;
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        self.sources.insert(
+            id,
+            MixerSource {
+                bus,
+                analyser: None,
+                ended: Rc::new(Cell::new(false)),
+            },
+        );
+        Ok(id)
+    }
+
+    // Set once the source has finished for good; `prune` then drops it.
+    pub fn ended_flag(&self, id: usize) -> Option<Rc<Cell<bool>>> {
+        Some(self.sources.get(&id)?.ended.clone())
+    }
+
+    pub fn prune(&mut self) -> Vec<usize> {
+        let ended: Vec<usize> = self
+            .sources
+            .iter()
+            .filter(|(_, source)| source.ended.get())
+            .map(|(&id, _)| id)
+            .collect();
+        for id in &ended {
+            if let Some(source) = self.sources.remove(id) {
+                source.disconnect();
+            }
+        }
+        ended
+    }
+
+    pub fn set_gain(&self, id: usize, value: f32) -> Result<(), JsValue> {
+        self.source(id)?.bus.gain().set_value(value);
+        Ok(())
+    }
+
+    pub fn set_level(&self, level: f32) {
+        self.mix.gain().set_value(level);
+        self.level.gain().set_value(level);
+    }
+
+    pub fn attach_analyser(
+        &mut self,
+        id: usize,
+        context: &AudioContext,
+        template: &AnalyserNode,
+    ) -> Result<(), JsValue> {
+        let source = self
+            .sources
+            .get_mut(&id)
+            .ok_or_else(|| JsValue::from_str("No source with that id"))?;
+        if source.analyser.is_none() {
+            let analyser = context.create_analyser()?;
+            analyser.set_fft_size(template.fft_size());
+            analyser.set_smoothing_time_constant(template.smoothing_time_constant());
+            source.bus.connect_with_audio_node(&analyser)?;
+            source.analyser = Some(analyser);
+        }
+        Ok(())
+    }
+
+    pub fn analyser(&self, id: usize) -> Option<&AnalyserNode> {
+        self.sources.get(&id)?.analyser.as_ref()
+    }
+
+    pub fn clear(&mut self) {
+        for (_, source) in self.sources.drain() {
+            source.disconnect();
+        }
+    }
+
+    fn source(&self, id: usize) -> Result<&MixerSource, JsValue> {
+        self.sources
+            .get(&id)
+            .ok_or_else(|| JsValue::from_str("No source with that id"))
+    }
+}
@@ -0,0 +1,82 @@
+use js_sys::Promise;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{AudioBuffer, OfflineAudioContext};
+
+const RENDER_QUANTUM: f64 = 128.0;
+
+#[wasm_bindgen]
+extern "C" {
+    // web-sys does not expose the time-based OfflineAudioContext.suspend().
+    type SuspendableContext;
+
+    #[wasm_bindgen(method, catch)]
+    fn suspend(this: &SuspendableContext, time: f64) -> Result<Promise, JsValue>;
+}
+
+fn suspend_at(context: &OfflineAudioContext, time: f64) -> Result<Promise, JsValue> {
+    context.unchecked_ref::<SuspendableContext>().suspend(time)
+}
+
+pub struct AnalyserSettings {
+    pub fft_size: u32,
+    pub smoothing: f64,
+}
+
+// Steps an OfflineAudioContext through the buffer by suspending at every
+// frame boundary, handing each frame's spectrum to `on_frame` before resuming.
+pub async fn render<F>(
+    buffer: &AudioBuffer,
+    settings: &AnalyserSettings,
+    fps: f64,
+    mut on_frame: F,
+) -> Result<usize, JsValue>
+where
+    F: FnMut(usize, f64, &[u8]) -> Result<Option<Promise>, JsValue>,
+{
+    let sample_rate = buffer.sample_rate();
+    if !fps.is_finite() || fps <= 0.0 || fps > sample_rate as f64 / RENDER_QUANTUM {
+        return Err(JsValue::from_str("Offline frame rate is out of range"));
+    }
+
+    let context = OfflineAudioContext::new_with_number_of_channels_and_length_and_sample_rate(
+        buffer.number_of_channels(),
+        buffer.length(),
+        sample_rate,
+    )?;
+    let analyser = context.create_analyser()?;
+    analyser.set_fft_size(settings.fft_size);
+    analyser.set_smoothing_time_constant(settings.smoothing);
+    analyser.connect_with_audio_node(&context.destination())?;
+
+    let source = context.create_buffer_source()?;
+    source.set_buffer(Some(buffer));
+    source.connect_with_audio_node(&analyser)?;
+    source.start()?;
+
+    let frames = (buffer.duration() * fps).floor() as usize;
+    let mut data = vec![0u8; analyser.frequency_bin_count() as usize];
+
+    let mut suspended = (frames > 0).then(|| suspend_at(&context, 0.0)).transpose()?;
+    let rendering = JsFuture::from(context.start_rendering()?);
+
+    for frame in 0..frames {
+        if let Some(suspend) = suspended.take() {
+            JsFuture::from(suspend).await?;
+        }
+        let time = frame as f64 / fps;
+        analyser.get_byte_frequency_data(&mut data);
+        if let Some(pending) = on_frame(frame, time, &data)? {
+            JsFuture::from(pending).await?;
+        }
+
+        if frame + 1 < frames {
+            suspended = Some(suspend_at(&context, (frame + 1) as f64 / fps)?);
+        }
+        JsFuture::from(context.resume()?).await?;
+    }
+
+    rendering.await?;
+    Ok(frames)
+}
@@ -0,0 +1,89 @@
+use std::collections::VecDeque;
+
+const HISTORY_FRAMES: usize = 43;
+const MIN_FLUX: f64 = 0.004;
+const MIN_INTERVAL_MS: f64 = 100.0;
+const DEFAULT_SENSITIVITY: f64 = 0.5;
+
+pub struct OnsetDetector {
+    previous: Vec<u8>,
+    history: VecDeque<f64>,
+    sensitivity: f64,
+    last_onset_at: f64,
+    flux: f64,
+}
+
+impl OnsetDetector {
+    pub fn new() -> OnsetDetector {
+        OnsetDetector {
+            previous: Vec::new(),
+            history: VecDeque::with_capacity(HISTORY_FRAMES),
+            sensitivity: DEFAULT_SENSITIVITY,
+            last_onset_at: f64::NEG_INFINITY,
+            flux: 0.0,
+        }
+    }
+
+    pub fn set_sensitivity(&mut self, sensitivity: f64) {
+        self.sensitivity = sensitivity.clamp(0.0, 1.0);
+    }
+
+    pub fn sensitivity(&self) -> f64 {
+        self.sensitivity
+    }
+
+    pub fn flux(&self) -> f64 {
+        self.flux
+    }
+
+    pub fn process(&mut self, spectrum: &[u8], now: f64) -> Option<f64> {
+        if self.previous.len() != spectrum.len() {
+            self.previous = spectrum.to_vec();
+            self.history.clear();
+            self.flux = 0.0;
+            return None;
+        }
+
+        let flux = spectral_flux(&self.previous, spectrum);
+        self.previous.copy_from_slice(spectrum);
+        self.flux = flux;
+
+        let onset = self.is_onset(flux, now);
+
+        if self.history.len() == HISTORY_FRAMES {
+            self.history.pop_front();
+        }
+        self.history.push_back(flux);
+
+        onset.then(|| {
+            self.last_onset_at = now;
+            flux
+        })
+    }
+
+    fn is_onset(&self, flux: f64, now: f64) -> bool {
+        if self.history.len() < HISTORY_FRAMES / 2 || now - self.last_onset_at < MIN_INTERVAL_MS {
+            return false;
+        }
+
+        let count = self.history.len() as f64;
+        let mean = self.history.iter().sum::<f64>() / count;
+        let variance = self.history.iter().map(|&v| (v - mean).powi(2)).sum::<f64>() / count;
+        let multiplier = 3.0 - 2.5 * self.sensitivity;
+
+        flux > MIN_FLUX && flux > mean + multiplier * variance.sqrt()
+    }
+}
+
+pub fn spectral_flux(previous: &[u8], current: &[u8]) -> f64 {
+    if current.is_empty() {
+        return 0.0;
+    }
+
+    let rise: u32 = previous
+        .iter()
+        .zip(current)
+        .map(|(&before, &after)| after.saturating_sub(before) as u32)
+        .sum();
+    rise as f64 / (current.len() as f64 * 255.0)
+}
@@ -0,0 +1,156 @@
+use crate::background::BackgroundKind;
+use crate::camera::CameraConfig;
+use crate::layer::BlendMode;
+use crate::rainbow_style::ParticleConfig;
+use crate::ring_style::Symmetry;
+use crate::weighting::Weighting;
+use crate::StyleType;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
+use tsify_next::Tsify;
+use wasm_bindgen::prelude::*;
+
+#[derive(Default, Serialize, Deserialize, Tsify)]
+#[tsify(from_wasm_abi)]
+#[serde(default)]
+pub struct ProcessorOptions {
+    /// Power of two between 32 and 32768.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[tsify(optional)]
+    pub fft_size: Option<u32>,
+    /// Analyser smoothing time constant, 0..1.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[tsify(optional)]
+    pub smoothing: Option<f64>,
+    /// Omit or pass null to follow `prefers-reduced-motion`.
+    #[serde(deserialize_with = "present", skip_serializing_if = "Option::is_none")]
+    #[tsify(type = "boolean | null")]
+    pub reduced_motion: Option<Option<bool>>,
+    /// 0 renders every animation frame.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[tsify(optional)]
+    pub max_fps: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[tsify(optional)]
+    pub adaptive_quality: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[tsify(optional)]
+    pub color_by_pitch: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[tsify(optional)]
+    pub show_album_art: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[tsify(optional)]
+    pub onset_sensitivity: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[tsify(optional)]
+    pub pan_visuals: Option<bool>,
+    /// Keep styles gently animating while nothing is playing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[tsify(optional)]
+    pub idle_animation: Option<bool>,
+    /// Frequency weighting applied to the spectrum before it is drawn.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[tsify(optional, type = "WeightingName")]
+    pub weighting: Option<Weighting>,
+    /// null disables the bass-reactive camera.
+    #[serde(deserialize_with = "present", skip_serializing_if = "Option::is_none")]
+    #[tsify(type = "CameraOptions | null")]
+    pub camera: Option<Option<CameraConfig>>,
+    /// Seconds of streamed audio kept behind the playhead.
+    #[serde(deserialize_with = "present", skip_serializing_if = "Option::is_none")]
+    #[tsify(type = "number | null")]
+    pub buffer_retention: Option<Option<f64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[tsify(optional)]
+    pub pause_audio_when_hidden: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Tsify)]
+#[tsify(from_wasm_abi)]
+pub struct InstanceOptions {
+    #[tsify(type = "StyleName")]
+    pub style: StyleType,
+    /// Bind the instance to one source's analyser instead of the mix. That analyser
+    /// taps the source's bus ahead of the loudness normalizer, so the canvas follows
+    /// `set_source_gain` but not the level set by `enable_compressor` normalization.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[tsify(optional)]
+    pub source: Option<usize>,
+    #[serde(default)]
+    #[tsify(type = "BackgroundOptions | null")]
+    pub background: Option<BackgroundKind>,
+    /// Applies to the base style; each layer carries its own.
+    #[serde(flatten)]
+    pub effects: StyleOptions,
+    #[serde(default)]
+    pub layers: Vec<LayerOptions>,
+}
+
+#[derive(Serialize, Deserialize, Tsify)]
+pub struct LayerOptions {
+    #[tsify(type = "StyleName")]
+    pub style: StyleType,
+    #[tsify(type = "BlendName")]
+    pub blend: BlendMode,
+    /// 0..1, defaults to 1.
+    #[serde(default = "full_opacity")]
+    pub opacity: f64,
+    #[serde(flatten)]
+    pub effects: StyleOptions,
+}
+
+#[derive(Default, Serialize, Deserialize, Tsify)]
+pub struct StyleOptions {
+    /// Visualizer only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[tsify(optional, type = "SymmetryName")]
+    pub symmetry: Option<Symmetry>,
+    /// Degrees per frame, used with `symmetry`.
+    #[serde(default)]
+    pub rotation_speed: f64,
+    /// Bg only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[tsify(optional, type = "ParticleOptions")]
+    pub particles: Option<ParticleConfig>,
+}
+
+#[derive(Default, Deserialize, Tsify)]
+#[tsify(from_wasm_abi)]
+#[serde(default)]
+pub struct FetchOptions {
+    /// Sent with every request for the track, e.g. `Authorization`.
+    #[tsify(type = "Record<string, string>")]
+    pub headers: HashMap<String, String>,
+    #[tsify(optional)]
+    pub credentials: Option<CredentialsMode>,
+    /// Called when a request is rejected with 401/403. Resolve to headers that
+    /// replace the matching ones and the request is retried; resolve to null
+    /// to give up.
+    #[serde(with = "serde_wasm_bindgen::preserve")]
+    #[tsify(
+        type = "() => Record<string, string> | null | Promise<Record<string, string> | null>"
+    )]
+    pub on_token_refresh: JsValue,
+}
+
+#[derive(Clone, Copy, Deserialize, Tsify)]
+#[serde(rename_all = "kebab-case")]
+pub enum CredentialsMode {
+    Omit,
+    SameOrigin,
+    Include,
+}
+
+fn full_opacity() -> f64 {
+    1.0
+}
+
+// Keeps an explicit null apart from a missing field, which leaves the setting untouched.
+fn present<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::deserialize(deserializer).map(Some)
+}
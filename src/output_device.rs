@@ -0,0 +1,46 @@
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{MediaDeviceInfo, MediaDeviceKind};
+
+pub fn supports_sink_id(target: &JsValue) -> bool {
+    js_sys::Reflect::get(target, &"setSinkId".into())
+        .map(|method| method.is_function())
+        .unwrap_or(false)
+}
+
+pub async fn set_sink_id(target: &JsValue, device_id: &str) -> Result<(), JsValue> {
+    let method: js_sys::Function = js_sys::Reflect::get(target, &"setSinkId".into())?
+        .dyn_into()
+        .map_err(|_| JsValue::from_str("setSinkId is not supported"))?;
+    let promise: js_sys::Promise = method
+        .call1(target, &JsValue::from_str(device_id))?
+        .dyn_into()?;
+    JsFuture::from(promise).await?;
+    Ok(())
+}
+
+pub async fn enumerate_output_devices() -> Result<js_sys::Array, JsValue> {
+    let media_devices = web_sys::window()
+        .ok_or_else(|| JsValue::from_str("No window found"))?
+        .navigator()
+        .media_devices()?;
+    let devices: js_sys::Array = JsFuture::from(media_devices.enumerate_devices()?)
+        .await?
+        .dyn_into()?;
+
+    let outputs = js_sys::Array::new();
+    for device in devices.iter() {
+        let device: MediaDeviceInfo = device.dyn_into()?;
+        if device.kind() != MediaDeviceKind::Audiooutput {
+            continue;
+        }
+
+        let entry = js_sys::Object::new();
+        js_sys::Reflect::set(&entry, &"deviceId".into(), &JsValue::from_str(&device.device_id()))?;
+        js_sys::Reflect::set(&entry, &"label".into(), &JsValue::from_str(&device.label()))?;
+        outputs.push(&entry);
+    }
+
+    Ok(outputs)
+}
@@ -0,0 +1,65 @@
+use wasm_bindgen::prelude::*;
+use web_sys::{CanvasRenderingContext2d, Path2d};
+
+pub const PALETTE_SIZE: usize = 90;
+
+pub fn palette(function: &str, alpha: &str) -> Vec<JsValue> {
+    (0..PALETTE_SIZE)
+        .map(|i| {
+            let hue = i as f64 * 360.0 / PALETTE_SIZE as f64;
+            JsValue::from_str(&format!("{}({}, 100%, 50%{})", function, hue, alpha))
+        })
+        .collect()
+}
+
+pub fn palette_index(hue: f64) -> usize {
+    (hue.rem_euclid(360.0) * PALETTE_SIZE as f64 / 360.0) as usize % PALETTE_SIZE
+}
+
+#[derive(Clone)]
+pub struct PathBatch {
+    paths: Vec<Option<Path2d>>,
+}
+
+impl PathBatch {
+    pub fn new() -> PathBatch {
+        PathBatch {
+            paths: vec![None; PALETTE_SIZE],
+        }
+    }
+
+    pub fn path(&mut self, color: usize) -> Result<&Path2d, JsValue> {
+        let slot = &mut self.paths[color];
+        if slot.is_none() {
+            *slot = Some(Path2d::new()?);
+        }
+        Ok(slot.as_ref().unwrap())
+    }
+
+    pub fn fill(&mut self, ctx: &CanvasRenderingContext2d, palette: &[JsValue]) {
+        self.fill_retained(ctx, palette);
+        self.clear();
+    }
+
+    pub fn fill_retained(&self, ctx: &CanvasRenderingContext2d, palette: &[JsValue]) {
+        for (color, slot) in self.paths.iter().enumerate() {
+            if let Some(ref path) = slot {
+                ctx.set_fill_style(&palette[color]);
+                ctx.fill_with_path_2d(path);
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.paths.iter_mut().for_each(|slot| *slot = None);
+    }
+
+    pub fn stroke(&mut self, ctx: &CanvasRenderingContext2d, palette: &[JsValue]) {
+        for (color, slot) in self.paths.iter_mut().enumerate() {
+            if let Some(path) = slot.take() {
+                ctx.set_stroke_style(&palette[color]);
+                ctx.stroke_with_path(&path);
+            }
+        }
+    }
+}
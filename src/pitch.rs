@@ -0,0 +1,115 @@
+use wasm_bindgen::prelude::*;
+use web_sys::{AnalyserNode, AudioNode, BaseAudioContext};
+
+const YIN_THRESHOLD: f64 = 0.15;
+const MIN_RMS: f64 = 0.01;
+// Long enough for YIN to reach lags down to ~45 Hz at 48 kHz.
+const WINDOW_SIZE: u32 = 2048;
+const NOTE_NAMES: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct PitchNote {
+    pub frequency: f64,
+    #[wasm_bindgen(getter_with_clone)]
+    pub name: String,
+    pub octave: i32,
+    pub cents: f64,
+}
+
+impl PitchNote {
+    pub fn from_frequency(frequency: f64) -> PitchNote {
+        let midi = 69.0 + 12.0 * (frequency / 440.0).log2();
+        let nearest = midi.round();
+        let index = nearest as i32;
+        PitchNote {
+            frequency,
+            name: NOTE_NAMES[index.rem_euclid(12) as usize].to_string(),
+            octave: index.div_euclid(12) - 1,
+            cents: (midi - nearest) * 100.0,
+        }
+    }
+}
+
+pub fn pitch_class(frequency: f64) -> usize {
+    let midi = 69.0 + 12.0 * (frequency / 440.0).log2();
+    (midi.round() as i32).rem_euclid(12) as usize
+}
+
+pub struct PitchDetector {
+    analyser: AnalyserNode,
+    samples: Vec<f32>,
+    difference: Vec<f64>,
+}
+
+impl PitchDetector {
+    pub fn new(context: &BaseAudioContext, input: &AudioNode) -> Result<PitchDetector, JsValue> {
+        let analyser = context.create_analyser()?;
+        analyser.set_fft_size(WINDOW_SIZE);
+        input.connect_with_audio_node(&analyser)?;
+        Ok(PitchDetector {
+            analyser,
+            samples: vec![0.0; WINDOW_SIZE as usize],
+            difference: Vec::new(),
+        })
+    }
+
+    pub fn detect(&mut self, sample_rate: f64) -> Option<f64> {
+        self.analyser.get_float_time_domain_data(&mut self.samples);
+        self.yin(sample_rate)
+    }
+
+    fn yin(&mut self, sample_rate: f64) -> Option<f64> {
+        let samples = &self.samples;
+        let half = samples.len() / 2;
+        if half < 3 {
+            return None;
+        }
+
+        let energy = samples.iter().map(|&s| (s as f64).powi(2)).sum::<f64>();
+        if (energy / samples.len() as f64).sqrt() < MIN_RMS {
+            return None;
+        }
+
+        let difference = &mut self.difference;
+        difference.clear();
+        difference.resize(half, 0.0);
+        for (tau, value) in difference.iter_mut().enumerate().skip(1) {
+            *value = samples[..half]
+                .iter()
+                .zip(&samples[tau..tau + half])
+                .map(|(&a, &b)| (a as f64 - b as f64).powi(2))
+                .sum();
+        }
+
+        difference[0] = 1.0;
+        let mut running = 0.0;
+        for (tau, value) in difference.iter_mut().enumerate().skip(1) {
+            running += *value;
+            *value = if running > 0.0 {
+                *value * tau as f64 / running
+            } else {
+                1.0
+            };
+        }
+
+        let mut tau = (2..half).find(|&tau| difference[tau] < YIN_THRESHOLD)?;
+        while tau + 1 < half && difference[tau + 1] < difference[tau] {
+            tau += 1;
+        }
+
+        let refined = if tau + 1 < half {
+            let (before, at, after) = (difference[tau - 1], difference[tau], difference[tau + 1]);
+            let curvature = before + after - 2.0 * at;
+            if curvature.abs() > f64::EPSILON {
+                tau as f64 + (before - after) / (2.0 * curvature)
+            } else {
+                tau as f64
+            }
+        } else {
+            tau as f64
+        };
+
+        Some(sample_rate / refined)
+    }
+}
@@ -0,0 +1,26 @@
+use crate::metadata::SharedMetadata;
+use crate::request::RequestOptions;
+use crate::PlaybackMode;
+use web_sys::{AudioBuffer, GainNode, HtmlMediaElement};
+
+pub enum Standby {
+    Element(HtmlMediaElement, GainNode, PlaybackMode),
+    Buffer(AudioBuffer),
+}
+
+pub struct Preloaded {
+    pub url: String,
+    pub request: RequestOptions,
+    pub standby: Standby,
+    pub metadata: SharedMetadata,
+}
+
+impl Preloaded {
+    pub fn discard(self) {
+        if let Standby::Element(element, _, _) = self.standby {
+            let _ = element.remove_attribute("src");
+            element.load();
+        }
+        self.metadata.clear();
+    }
+}
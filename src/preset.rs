@@ -0,0 +1,14 @@
+use crate::options::{InstanceOptions, ProcessorOptions};
+use serde::{Deserialize, Serialize};
+
+pub const PRESET_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+pub struct Preset {
+    #[serde(default)]
+    pub version: u32,
+    #[serde(flatten)]
+    pub settings: ProcessorOptions,
+    #[serde(default)]
+    pub instances: Vec<InstanceOptions>,
+}
@@ -1,13 +1,58 @@
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 use wasm_bindgen::JsValue;
+use serde::{Deserialize, Serialize};
+use tsify_next::Tsify;
 use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement};
+use std::collections::VecDeque;
 use std::f64::consts::PI;
+use crate::features::{self, SpectralFeatures};
+use crate::background::Background;
+use crate::palette::{palette, palette_index, PathBatch};
+use crate::rng::Rng;
+
+const PARTICLE_COUNT: usize = 100;
+const MIN_PARTICLES: usize = 20;
+const REDUCED_MOTION_HUE_STEP: f64 = 0.1;
+const REDUCED_MOTION_SPEED_SCALE: f64 = 0.25;
+const BURST_SPEED_SCALE: f64 = 3.0;
+const BURST_DECAY: f64 = 0.9;
+const FADE_LEVELS: usize = 4;
+const PAN_DRIFT: f64 = 1.5;
 
 #[wasm_bindgen]
-extern "C" {
-    #[wasm_bindgen(js_namespace = Math)]
-    fn random() -> f64;
+#[derive(Clone, Serialize, Deserialize, Tsify)]
+#[serde(rename = "ParticleOptions", default)]
+pub struct ParticleConfig {
+    pub count: u32,
+    pub min_size: f64,
+    pub max_size: f64,
+    pub speed: f64,
+    pub attraction: f64,
+    pub trail_length: u32,
+    pub lifetime: u32,
+}
+
+#[wasm_bindgen]
+impl ParticleConfig {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> ParticleConfig {
+        ParticleConfig {
+            count: PARTICLE_COUNT as u32,
+            min_size: 1.0,
+            max_size: 4.0,
+            speed: 1.0,
+            attraction: 0.0,
+            trail_length: 0,
+            lifetime: 0,
+        }
+    }
+}
+
+impl Default for ParticleConfig {
+    fn default() -> Self {
+        ParticleConfig::new()
+    }
 }
 
 #[derive(Clone)]
@@ -18,11 +63,21 @@ pub struct Bg {
     height: u32,
     center_x: f64,
     center_y: f64,
-    previous_values: Vec<f64>,
     hue: f64,
     brightness: f64,
     saturation: f64,
     particles: Vec<Particle>,
+    config: ParticleConfig,
+    quality: f64,
+    reduced_motion: bool,
+    particle_palette: Vec<JsValue>,
+    batches: Vec<PathBatch>,
+    trail_batches: Vec<PathBatch>,
+    burst: f64,
+    features: Option<SpectralFeatures>,
+    background: Option<Background>,
+    pan: f64,
+    rng: Rng,
 }
 
 #[wasm_bindgen]
@@ -39,7 +94,11 @@ impl Bg {
         let center_x = width as f64 / 2.0;
         let center_y = height as f64 / 2.0;
 
-        let particles = (0..100).map(|_| Particle::new(width, height)).collect();
+        let config = ParticleConfig::new();
+        let mut rng = Rng::from_entropy();
+        let particles = (0..config.count)
+            .map(|_| Particle::new(width, height, &config, &mut rng))
+            .collect();
 
         Ok(Bg {
             ctx,
@@ -47,64 +106,205 @@ impl Bg {
             height,
             center_x,
             center_y,
-            previous_values: vec![0.0; 64],
             hue: 0.0,
             brightness: 50.0,
             saturation: 100.0,
             particles,
+            config,
+            quality: 1.0,
+            reduced_motion: false,
+            particle_palette: palette("hsla", ", 0.8"),
+            batches: vec![PathBatch::new(); FADE_LEVELS],
+            trail_batches: vec![PathBatch::new(); FADE_LEVELS],
+            burst: 0.0,
+            features: None,
+            background: None,
+            pan: 0.0,
+            rng,
         })
     }
 
+    #[wasm_bindgen]
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        self.center_x = width as f64 / 2.0;
+        self.center_y = height as f64 / 2.0;
+        self.respawn_particles();
+    }
+
+    #[wasm_bindgen]
+    pub fn set_particle_config(&mut self, config: &ParticleConfig) {
+        let mut config = config.clone();
+        if config.max_size < config.min_size {
+            std::mem::swap(&mut config.min_size, &mut config.max_size);
+        }
+        config.min_size = config.min_size.max(0.1);
+        self.config = config;
+
+        self.respawn_particles();
+        self.set_quality(self.quality);
+    }
+
+    #[wasm_bindgen]
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+        self.hue = 0.0;
+        self.brightness = 50.0;
+        self.respawn_particles();
+    }
+
+    #[wasm_bindgen]
+    pub fn particle_config(&self) -> ParticleConfig {
+        self.config.clone()
+    }
+
+    #[wasm_bindgen]
+    pub fn set_context(&mut self, ctx: CanvasRenderingContext2d) {
+        self.ctx = ctx;
+    }
+
+    #[wasm_bindgen]
+    pub fn set_background(&mut self, background: Option<Background>) {
+        self.background = background;
+    }
+
+    #[wasm_bindgen]
+    pub fn set_reduced_motion(&mut self, enabled: bool) {
+        self.reduced_motion = enabled;
+    }
+
+    #[wasm_bindgen]
+    pub fn set_features(&mut self, features: &SpectralFeatures) {
+        self.features = Some(*features);
+    }
+
+    #[wasm_bindgen]
+    pub fn trigger_onset(&mut self) {
+        self.burst = 1.0;
+    }
+
+    #[wasm_bindgen]
+    pub fn set_pan(&mut self, pan: f64) {
+        self.pan = pan.clamp(-1.0, 1.0);
+    }
+
+    #[wasm_bindgen]
+    pub fn set_quality(&mut self, quality: f64) {
+        self.quality = quality.clamp(0.0, 1.0);
+        let max_count = self.config.count as usize;
+        let count = (max_count as f64 * self.quality).round() as usize;
+        let count = count.clamp(MIN_PARTICLES.min(max_count), max_count);
+        let (width, height) = (self.width, self.height);
+        if count < self.particles.len() {
+            self.particles.truncate(count);
+        } else {
+            while self.particles.len() < count {
+                self.particles
+                    .push(Particle::new(width, height, &self.config, &mut self.rng));
+            }
+        }
+    }
+
     #[wasm_bindgen]
     pub fn draw(&mut self, audio_data: &[u8]) {
         let ctx = &self.ctx;
 
-        let background_color = format!(
-            "hsl({}, {}%, {}%)",
-            self.hue as i32,
-            self.saturation as i32,
-            self.brightness as i32
-        );
-        ctx.set_fill_style(&JsValue::from_str(&background_color));
-        ctx.fill_rect(0.0, 0.0, self.width as f64, self.height as f64);
+        match self.background {
+            Some(ref background) => background.paint(ctx),
+            None => {
+                let background_color = format!(
+                    "hsl({}, {}%, {}%)",
+                    self.hue as i32,
+                    self.saturation as i32,
+                    self.brightness as i32
+                );
+                ctx.set_fill_style(&JsValue::from_str(&background_color));
+                ctx.fill_rect(0.0, 0.0, self.width as f64, self.height as f64);
+            }
+        }
 
         ctx.save();
         ctx.translate(self.center_x, self.center_y).unwrap();
 
-        {
-            let particles = &mut self.particles;
-            let hue = self.hue;
-            Bg::draw_particles(particles, hue, ctx, audio_data, self.width, self.height);
-        }
+        self.draw_particles(audio_data);
 
-        ctx.restore();
+        self.ctx.restore();
 
-        self.hue = (self.hue + 1.0) % 360.0;
-        self.brightness = (self.brightness + (random() * 10.0 - 5.0)) % 100.0;
+        if self.reduced_motion {
+            self.hue = (self.hue + REDUCED_MOTION_HUE_STEP) % 360.0;
+        } else {
+            self.hue = (self.hue + 1.0) % 360.0;
+            self.brightness = (self.brightness + self.rng.signed() * 5.0) % 100.0;
+        }
     }
 
-    fn draw_particles(
-        particles: &mut Vec<Particle>,
-        hue: f64,
-        ctx: &CanvasRenderingContext2d,
-        audio_data: &[u8],
-        width: u32,
-        height: u32,
-    ) {
-        let treble = audio_data.iter().skip(10).take(20).map(|&x| x as f64).sum::<f64>() / 20.0;
+    fn respawn_particles(&mut self) {
+        let (width, height) = (self.width, self.height);
+        let count = self.particles.len();
+        let (config, rng) = (&self.config, &mut self.rng);
+        self.particles = (0..count).map(|_| Particle::new(width, height, config, rng)).collect();
+    }
+
+    fn draw_particles(&mut self, audio_data: &[u8]) {
+        let brightness = match self.features {
+            Some(features) => features.brightness,
+            None => features::brightness(audio_data),
+        };
+        let drive = (brightness.sqrt() * 2.0).min(1.0) * 255.0;
+
+        let speed_scale = if self.reduced_motion {
+            REDUCED_MOTION_SPEED_SCALE
+        } else {
+            1.0 + self.burst * BURST_SPEED_SCALE
+        };
+        self.burst *= BURST_DECAY;
 
-        for particle in particles.iter_mut() {
-            particle.update(treble, width, height);
+        let lifetime = self.config.lifetime as f64;
+        let drift = self.pan * PAN_DRIFT;
+        for particle in self.particles.iter_mut() {
+            particle.update(
+                drive * speed_scale,
+                drift,
+                (self.width, self.height),
+                &self.config,
+                &mut self.rng,
+            );
 
-            ctx.set_fill_style(&JsValue::from_str(&format!(
-                "hsla({}, 100%, 50%, 0.8)",
-                (hue + particle.lifetime) % 360.0
-            )));
+            let particle_hue = if self.reduced_motion { self.hue } else { self.hue + particle.age };
+            let color = palette_index(particle_hue);
+            let level = if lifetime > 0.0 {
+                ((particle.age / lifetime) * FADE_LEVELS as f64) as usize
+            } else {
+                0
+            };
+            let level = level.min(FADE_LEVELS - 1);
 
-            ctx.begin_path();
-            ctx.arc(particle.x, particle.y, particle.size, 0.0, PI * 2.0).unwrap();
-            ctx.fill();
+            if let Some(&(x, y)) = particle.trail.front() {
+                if let Ok(trail) = self.trail_batches[level].path(color) {
+                    trail.move_to(x, y);
+                    for &(x, y) in particle.trail.iter().skip(1) {
+                        trail.line_to(x, y);
+                    }
+                    trail.line_to(particle.x, particle.y);
+                }
+            }
+
+            let Ok(path) = self.batches[level].path(color) else {
+                continue;
+            };
+            path.move_to(particle.x + particle.size, particle.y);
+            let _ = path.arc(particle.x, particle.y, particle.size, 0.0, PI * 2.0);
         }
+
+        let ctx = &self.ctx;
+        ctx.set_line_width(self.config.min_size);
+        for (level, (batch, trail)) in self.batches.iter_mut().zip(&mut self.trail_batches).enumerate() {
+            ctx.set_global_alpha(1.0 - level as f64 / FADE_LEVELS as f64);
+            trail.stroke(ctx, &self.particle_palette);
+            batch.fill(ctx, &self.particle_palette);
+        }
+        ctx.set_global_alpha(1.0);
     }
 }
 
@@ -113,30 +313,77 @@ struct Particle {
     x: f64,
     y: f64,
     size: f64,
-    lifetime: f64,
+    age: f64,
     speed_x: f64,
     speed_y: f64,
+    trail: VecDeque<(f64, f64)>,
 }
 
 impl Particle {
-    fn new(width: u32, height: u32) -> Particle {
+    fn new(width: u32, height: u32, config: &ParticleConfig, rng: &mut Rng) -> Particle {
         Particle {
-            x: (random() * width as f64) - (width as f64 / 2.0),
-            y: (random() * height as f64) - (height as f64 / 2.0),
-            size: random() * 3.0 + 1.0,
-            lifetime: 0.0,
-            speed_x: random() * 2.0 - 1.0,
-            speed_y: random() * 2.0 - 1.0,
+            x: (rng.next_f64() * width as f64) - (width as f64 / 2.0),
+            y: (rng.next_f64() * height as f64) - (height as f64 / 2.0),
+            size: config.min_size + rng.next_f64() * (config.max_size - config.min_size),
+            age: 0.0,
+            speed_x: rng.signed() * config.speed,
+            speed_y: rng.signed() * config.speed,
+            trail: VecDeque::with_capacity(config.trail_length as usize),
         }
     }
 
-    fn update(&mut self, treble: f64, width: u32, height: u32) {
-        self.x += self.speed_x * treble / 255.0;
-        self.y += self.speed_y * treble / 255.0;
-        self.lifetime += 1.0;
+    fn update(
+        &mut self,
+        drive: f64,
+        drift: f64,
+        (width, height): (u32, u32),
+        config: &ParticleConfig,
+        rng: &mut Rng,
+    ) {
+        if config.trail_length > 0 {
+            if self.trail.len() >= config.trail_length as usize {
+                self.trail.pop_front();
+            }
+            self.trail.push_back((self.x, self.y));
+        }
+
+        if config.attraction != 0.0 {
+            let distance = self.x.hypot(self.y).max(1.0);
+            self.speed_x -= self.x / distance * config.attraction / 60.0;
+            self.speed_y -= self.y / distance * config.attraction / 60.0;
+        }
+
+        self.x += (self.speed_x + drift) * drive / 255.0;
+        self.y += self.speed_y * drive / 255.0;
+        self.age += 1.0;
 
-        if self.x > width as f64 / 2.0 || self.x < -(width as f64 / 2.0) || self.y > height as f64 / 2.0 || self.y < -(height as f64 / 2.0) {
-            *self = Particle::new(width, height);
+        let expired = config.lifetime > 0 && self.age >= config.lifetime as f64;
+        if expired || self.x > width as f64 / 2.0 || self.x < -(width as f64 / 2.0) || self.y > height as f64 / 2.0 || self.y < -(height as f64 / 2.0) {
+            *self = Particle::new(width, height, config, rng);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spawn(seed: u64) -> Vec<(f64, f64, f64, f64, f64)> {
+        let config = ParticleConfig::new();
+        let mut rng = Rng::new(seed);
+        (0..config.count)
+            .map(|_| Particle::new(640, 480, &config, &mut rng))
+            .map(|p| (p.x, p.y, p.size, p.speed_x, p.speed_y))
+            .collect()
+    }
+
+    #[test]
+    fn particles_spawn_deterministically_for_a_seed() {
+        assert_eq!(spawn(1234), spawn(1234));
+    }
+
+    #[test]
+    fn particles_differ_between_seeds() {
+        assert_ne!(spawn(1), spawn(2));
+    }
+}
@@ -0,0 +1,127 @@
+use crate::log;
+use futures::channel::oneshot;
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{
+    AnalyserNode, AudioContext, Blob, BlobEvent, HtmlCanvasElement, MediaRecorder,
+    MediaRecorderOptions, MediaStream, MediaStreamAudioDestinationNode, MediaStreamTrack,
+};
+
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct RecordingOptions {
+    pub fps: f64,
+    pub video_bits_per_second: u32,
+    #[wasm_bindgen(getter_with_clone)]
+    pub mime_type: String,
+}
+
+#[wasm_bindgen]
+impl RecordingOptions {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> RecordingOptions {
+        RecordingOptions {
+            fps: 60.0,
+            video_bits_per_second: 8_000_000,
+            mime_type: "video/webm;codecs=vp9,opus".to_string(),
+        }
+    }
+}
+
+impl Default for RecordingOptions {
+    fn default() -> Self {
+        RecordingOptions::new()
+    }
+}
+
+pub struct Recorder {
+    recorder: MediaRecorder,
+    stream: MediaStream,
+    audio_destination: MediaStreamAudioDestinationNode,
+    chunks: Rc<RefCell<Vec<Blob>>>,
+    _on_data: Closure<dyn FnMut(BlobEvent)>,
+}
+
+impl Recorder {
+    pub fn start(
+        canvas: &HtmlCanvasElement,
+        context: &AudioContext,
+        analyser: &AnalyserNode,
+        options: &RecordingOptions,
+    ) -> Result<Recorder, JsValue> {
+        let video = canvas.capture_stream_with_frame_request_rate(options.fps)?;
+        let audio_destination = context.create_media_stream_destination()?;
+        analyser.connect_with_audio_node(&audio_destination)?;
+
+        let tracks = video.get_video_tracks();
+        for track in audio_destination.stream().get_audio_tracks().iter() {
+            tracks.push(&track);
+        }
+        let stream = MediaStream::new_with_tracks(&tracks)?;
+
+        let recorder_options = MediaRecorderOptions::new();
+        if MediaRecorder::is_type_supported(&options.mime_type) {
+            recorder_options.set_mime_type(&options.mime_type);
+        } else {
+            log(&format!(
+                "{} is not supported, using the browser default format",
+                options.mime_type
+            ));
+        }
+        recorder_options.set_video_bits_per_second(options.video_bits_per_second);
+
+        let recorder =
+            MediaRecorder::new_with_media_stream_and_media_recorder_options(&stream, &recorder_options)?;
+
+        let chunks = Rc::new(RefCell::new(Vec::new()));
+        let on_data = {
+            let chunks = chunks.clone();
+            Closure::wrap(Box::new(move |event: BlobEvent| {
+                if let Some(data) = event.data() {
+                    if data.size() > 0.0 {
+                        chunks.borrow_mut().push(data);
+                    }
+                }
+            }) as Box<dyn FnMut(BlobEvent)>)
+        };
+        recorder.set_ondataavailable(Some(on_data.as_ref().unchecked_ref()));
+        recorder.start_with_time_slice(1000)?;
+
+        Ok(Recorder {
+            recorder,
+            stream,
+            audio_destination,
+            chunks,
+            _on_data: on_data,
+        })
+    }
+
+    pub async fn stop(self, analyser: &AnalyserNode) -> Result<Blob, JsValue> {
+        let (sender, receiver) = oneshot::channel::<()>();
+        let on_stop = Closure::once(move || {
+            let _ = sender.send(());
+        });
+        self.recorder.set_onstop(Some(on_stop.as_ref().unchecked_ref()));
+        self.recorder.stop()?;
+        receiver
+            .await
+            .map_err(|_| JsValue::from_str("Recorder stopped without finishing"))?;
+        self.recorder.set_onstop(None);
+        self.recorder.set_ondataavailable(None);
+
+        let _ = analyser.disconnect_with_audio_node(&self.audio_destination);
+        for track in self.stream.get_tracks().iter() {
+            track.unchecked_into::<MediaStreamTrack>().stop();
+        }
+
+        let parts = js_sys::Array::new();
+        for chunk in self.chunks.borrow().iter() {
+            parts.push(chunk);
+        }
+        let blob_options = web_sys::BlobPropertyBag::new();
+        blob_options.set_type(&self.recorder.mime_type());
+        Blob::new_with_blob_sequence_and_options(&parts, &blob_options)
+    }
+}
@@ -0,0 +1,119 @@
+use crate::options::{CredentialsMode, FetchOptions};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Headers, RequestCredentials, RequestInit, Response, Window};
+
+#[derive(Clone, Default)]
+pub struct RequestOptions {
+    // Shared so a refreshed token is picked up by every later fetch of the stream.
+    headers: Rc<RefCell<Vec<(String, String)>>>,
+    credentials: Option<RequestCredentials>,
+    on_token_refresh: Option<js_sys::Function>,
+}
+
+impl RequestOptions {
+    pub fn from_options(options: FetchOptions) -> Result<RequestOptions, JsValue> {
+        let credentials = options.credentials.map(|mode| match mode {
+            CredentialsMode::Omit => RequestCredentials::Omit,
+            CredentialsMode::SameOrigin => RequestCredentials::SameOrigin,
+            CredentialsMode::Include => RequestCredentials::Include,
+        });
+        let on_token_refresh = Some(options.on_token_refresh)
+            .filter(|callback| !callback.is_undefined() && !callback.is_null())
+            .map(|callback| callback.dyn_into::<js_sys::Function>())
+            .transpose()
+            .map_err(|_| JsValue::from_str("on_token_refresh must be a function"))?;
+
+        Ok(RequestOptions {
+            headers: Rc::new(RefCell::new(options.headers.into_iter().collect())),
+            credentials,
+            on_token_refresh,
+        })
+    }
+
+    // Native playback fetches through the media element, which cannot carry any of these.
+    pub fn is_default(&self) -> bool {
+        self.headers.borrow().is_empty()
+            && self.credentials.is_none()
+            && self.on_token_refresh.is_none()
+    }
+
+    pub async fn fetch(
+        &self,
+        window: &Window,
+        url: &str,
+        extra_headers: &[(&str, &str)],
+    ) -> Result<Response, JsValue> {
+        let response = self.send(window, url, extra_headers).await?;
+        if !matches!(response.status(), 401 | 403) {
+            return Ok(response);
+        }
+        let Some(ref refresh) = self.on_token_refresh else {
+            return Ok(response);
+        };
+
+        let mut refreshed = refresh.call0(&JsValue::NULL)?;
+        if let Some(promise) = refreshed.dyn_ref::<js_sys::Promise>() {
+            refreshed = JsFuture::from(promise.clone()).await?;
+        }
+        if refreshed.is_null() || refreshed.is_undefined() {
+            return Ok(response);
+        }
+
+        let refreshed: HashMap<String, String> = serde_wasm_bindgen::from_value(refreshed)
+            .map_err(|_| JsValue::from_str("Headers must be an object of name/value strings"))?;
+        for (name, value) in refreshed {
+            let mut headers = self.headers.borrow_mut();
+            headers.retain(|(existing, _)| !existing.eq_ignore_ascii_case(&name));
+            headers.push((name, value));
+        }
+        self.send(window, url, extra_headers).await
+    }
+
+    async fn send(
+        &self,
+        window: &Window,
+        url: &str,
+        extra_headers: &[(&str, &str)],
+    ) -> Result<Response, JsValue> {
+        let headers = Headers::new()?;
+        for (name, value) in self.headers.borrow().iter() {
+            headers.set(name, value)?;
+        }
+        for (name, value) in extra_headers {
+            headers.set(name, value)?;
+        }
+
+        let init = RequestInit::new();
+        init.set_headers(&headers);
+        if let Some(credentials) = self.credentials {
+            init.set_credentials(credentials);
+        }
+        JsFuture::from(window.fetch_with_str_and_init(url, &init))
+            .await?
+            .dyn_into()
+    }
+}
+
+// Header names are case-insensitive and their order carries no meaning.
+impl PartialEq for RequestOptions {
+    fn eq(&self, other: &RequestOptions) -> bool {
+        let headers = |options: &RequestOptions| {
+            let mut headers: Vec<_> = options
+                .headers
+                .borrow()
+                .iter()
+                .map(|(name, value)| (name.to_ascii_lowercase(), value.clone()))
+                .collect();
+            headers.sort();
+            headers
+        };
+        headers(self) == headers(other)
+            && self.credentials == other.credentials
+            && self.on_token_refresh == other.on_token_refresh
+    }
+}
@@ -0,0 +1,93 @@
+use crate::events::{EventCallbacks, PlayerEvent};
+use crate::log;
+use crate::stream::sleep;
+use std::future::Future;
+use wasm_bindgen::prelude::*;
+use web_sys::Window;
+
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub struct RetryConfig {
+    pub max_retries: u32,
+    pub initial_delay_ms: f64,
+    pub max_delay_ms: f64,
+}
+
+#[wasm_bindgen]
+impl RetryConfig {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> RetryConfig {
+        RetryConfig {
+            max_retries: 5,
+            initial_delay_ms: 500.0,
+            max_delay_ms: 8000.0,
+        }
+    }
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        RetryConfig::new()
+    }
+}
+
+pub struct Backoff {
+    config: Option<RetryConfig>,
+    attempt: u32,
+}
+
+impl Backoff {
+    pub fn new(config: Option<RetryConfig>) -> Backoff {
+        Backoff { config, attempt: 0 }
+    }
+
+    // Waits before the next attempt, or hands the error back once retries run out.
+    pub async fn wait(
+        &mut self,
+        window: &Window,
+        events: &EventCallbacks,
+        error: JsValue,
+    ) -> Result<(), JsValue> {
+        let Some(config) = self.config.filter(|config| self.attempt < config.max_retries) else {
+            return Err(error);
+        };
+        self.attempt += 1;
+        let delay = (config.initial_delay_ms * 2f64.powi(self.attempt as i32 - 1))
+            .min(config.max_delay_ms)
+            .max(0.0);
+        log(&format!(
+            "Stream stalled, retrying in {} ms (attempt {}/{})",
+            delay, self.attempt, config.max_retries
+        ));
+        events.emit1(PlayerEvent::Stalled, &self.attempt.into());
+        sleep(window, delay as i32).await
+    }
+
+    pub async fn run<T, F, Fut>(
+        &mut self,
+        window: &Window,
+        events: &EventCallbacks,
+        mut attempt: F,
+    ) -> Result<T, JsValue>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, JsValue>>,
+    {
+        loop {
+            match attempt().await {
+                Ok(value) => {
+                    self.succeeded(events);
+                    return Ok(value);
+                }
+                Err(error) => self.wait(window, events, error).await?,
+            }
+        }
+    }
+
+    pub fn succeeded(&mut self, events: &EventCallbacks) {
+        if self.attempt > 0 {
+            self.attempt = 0;
+            events.emit(PlayerEvent::Recovered);
+        }
+    }
+}
@@ -1,6 +1,43 @@
+use serde::{Deserialize, Serialize};
+use tsify_next::Tsify;
 use wasm_bindgen::prelude::*;
-use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement};
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, HtmlImageElement};
 use std::f64::consts::PI;
+use crate::background::Background;
+use crate::palette::{palette, palette_index, PathBatch};
+
+const MAX_BARS: usize = 128;
+const MIN_BARS: usize = 32;
+const REDUCED_MOTION_HUE_STEP: f64 = 0.05;
+const REDUCED_MOTION_MAX_BAR_STEP: f64 = 4.0;
+const PARTICLE_COUNT: usize = 20;
+const BURST_DECAY: f64 = 0.85;
+const BURST_CUTOFF: f64 = 0.05;
+const KEY_HUE_EASING: f64 = 0.05;
+const BASS_FRACTION: usize = 16;
+const MIN_WEDGE_BARS: usize = 8;
+const PAN_SHIFT: f64 = 0.25;
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize, Tsify)]
+#[serde(rename = "SymmetryName", rename_all = "lowercase")]
+pub enum Symmetry {
+    None,
+    Two,
+    Four,
+    Six,
+}
+
+impl Symmetry {
+    fn folds(self) -> usize {
+        match self {
+            Symmetry::None => 1,
+            Symmetry::Two => 2,
+            Symmetry::Four => 4,
+            Symmetry::Six => 6,
+        }
+    }
+}
 
 #[derive(Clone)]
 #[wasm_bindgen]
@@ -12,6 +49,21 @@ pub struct Visualizer {
     center_y: f64,
     previous_values: Vec<f64>,
     hue: f64,
+    reduced_motion: bool,
+    bars: usize,
+    angles: Vec<(f64, f64)>,
+    particle_angles: Vec<(f64, f64)>,
+    fill_palette: Vec<JsValue>,
+    glow_palette: Vec<JsValue>,
+    batch: PathBatch,
+    center_image: Option<HtmlImageElement>,
+    burst: f64,
+    key_hue: Option<f64>,
+    background: Option<Background>,
+    symmetry: Symmetry,
+    rotation: f64,
+    rotation_speed: f64,
+    pan: f64,
 }
 
 #[wasm_bindgen]
@@ -34,106 +86,289 @@ impl Visualizer {
             height,
             center_x,
             center_y,
-            previous_values: vec![0.0; 128],
+            previous_values: vec![0.0; MAX_BARS],
             hue: 0.0,
+            reduced_motion: false,
+            bars: MAX_BARS,
+            angles: angle_table(MAX_BARS, PI * 2.0),
+            particle_angles: angle_table(PARTICLE_COUNT, PI * 2.0),
+            fill_palette: palette("hsl", ""),
+            glow_palette: palette("hsla", ", 0.8"),
+            batch: PathBatch::new(),
+            center_image: None,
+            burst: 0.0,
+            key_hue: None,
+            background: None,
+            symmetry: Symmetry::None,
+            rotation: 0.0,
+            rotation_speed: 0.0,
+            pan: 0.0,
         })
     }
 
+    #[wasm_bindgen]
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        self.center_x = width as f64 / 2.0;
+        self.center_y = height as f64 / 2.0;
+    }
+
+    #[wasm_bindgen]
+    pub fn set_context(&mut self, ctx: CanvasRenderingContext2d) {
+        self.ctx = ctx;
+    }
+
+    #[wasm_bindgen]
+    pub fn set_background(&mut self, background: Option<Background>) {
+        self.background = background;
+    }
+
+    #[wasm_bindgen]
+    pub fn set_reduced_motion(&mut self, enabled: bool) {
+        self.reduced_motion = enabled;
+    }
+
+    #[wasm_bindgen]
+    pub fn trigger_onset(&mut self) {
+        self.burst = 1.0;
+    }
+
+    #[wasm_bindgen]
+    pub fn set_key_hue(&mut self, hue: Option<f64>) {
+        self.key_hue = hue;
+    }
+
+    #[wasm_bindgen]
+    pub fn set_center_image(&mut self, image: Option<HtmlImageElement>) {
+        self.center_image = image;
+    }
+
+    #[wasm_bindgen]
+    pub fn set_quality(&mut self, quality: f64) {
+        let bars = (MAX_BARS as f64 * quality.clamp(0.0, 1.0)).round() as usize;
+        let bars = bars.clamp(MIN_BARS, MAX_BARS);
+        if bars != self.bars {
+            self.bars = bars;
+            self.rebuild_angles();
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn set_symmetry(&mut self, symmetry: Symmetry, rotation_speed: f64) {
+        self.rotation_speed = rotation_speed.to_radians();
+        if symmetry != self.symmetry {
+            self.symmetry = symmetry;
+            self.rebuild_angles();
+        }
+        if self.rotation_speed == 0.0 {
+            self.rotation = 0.0;
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn symmetry(&self) -> Symmetry {
+        self.symmetry
+    }
+
+    #[wasm_bindgen]
+    pub fn rotation_speed(&self) -> f64 {
+        self.rotation_speed.to_degrees()
+    }
+
+    #[wasm_bindgen]
+    pub fn set_pan(&mut self, pan: f64) {
+        self.pan = pan.clamp(-1.0, 1.0);
+    }
+
     #[wasm_bindgen]
     pub fn draw(&mut self, audio_data: &[u8]) {
-        let ctx = &self.ctx;
-        
-        ctx.set_fill_style(&JsValue::from_str("rgba(0, 0, 0, 0.1)"));
-        ctx.fill_rect(0.0, 0.0, self.width as f64, self.height as f64);
+        match self.background {
+            Some(ref background) => background.paint(&self.ctx),
+            None => {
+                self.ctx.set_fill_style(&JsValue::from_str("rgba(0, 0, 0, 0.1)"));
+                self.ctx.fill_rect(0.0, 0.0, self.width as f64, self.height as f64);
+            }
+        }
         
-        ctx.save();
-        ctx.translate(self.center_x, self.center_y).unwrap();
+        self.ctx.save();
+        let pan_offset = self.pan * self.width as f64 * PAN_SHIFT;
+        self.ctx.translate(self.center_x + pan_offset, self.center_y).unwrap();
         
-        {
-            let previous_values = &mut self.previous_values;
-            let hue = &mut self.hue;
-            let width = self.width;
-            let height = self.height;
-            Visualizer::draw_circular_visualizer(ctx, audio_data, previous_values, hue, width, height);
-        }
+        self.draw_circular_visualizer(audio_data);
         
         self.draw_center_orb(audio_data);
         
-        self.draw_particles(audio_data);
+        if !self.reduced_motion {
+            self.draw_particles();
+        }
         
-        ctx.restore();
+        self.ctx.restore();
+
+        if !self.reduced_motion {
+            self.rotation = (self.rotation + self.rotation_speed).rem_euclid(PI * 2.0);
+        }
         
-        self.hue = (self.hue + 0.5) % 360.0;
+        if let Some(target) = self.key_hue {
+            let delta = (target - self.hue + 540.0).rem_euclid(360.0) - 180.0;
+            self.hue = (self.hue + delta * KEY_HUE_EASING).rem_euclid(360.0);
+        } else {
+            let hue_step = if self.reduced_motion { REDUCED_MOTION_HUE_STEP } else { 0.5 };
+            self.hue = (self.hue + hue_step) % 360.0;
+        }
     }
 
-    fn draw_circular_visualizer(
-        ctx: &CanvasRenderingContext2d,
-        audio_data: &[u8],
-        previous_values: &mut Vec<f64>,
-        hue: &mut f64,
-        _width: u32,
-        height: u32,
-    ) {
-        let bars = 128;
-        let radius = height as f64 * 0.3;
+    fn rebuild_angles(&mut self) {
+        let folds = self.symmetry.folds();
+        self.angles = angle_table(self.wedge_bars(), PI * 2.0 / folds as f64);
+    }
+
+    fn wedge_bars(&self) -> usize {
+        match self.symmetry.folds() {
+            1 => self.bars,
+            folds => (self.bars / folds).max(MIN_WEDGE_BARS),
+        }
+    }
+
+    fn draw_circular_visualizer(&mut self, audio_data: &[u8]) {
+        let ctx = &self.ctx;
+        let bars = self.wedge_bars();
+        let radius = self.height as f64 * 0.3;
+        let hue_spread = MAX_BARS as f64 / bars as f64;
+        let max_step = if self.reduced_motion {
+            Some(REDUCED_MOTION_MAX_BAR_STEP)
+        } else {
+            None
+        };
 
         for i in 0..bars {
-            let value = audio_data[i] as f64;
-            let smoothed_value = (value + previous_values[i]) / 2.0;
-            previous_values[i] = smoothed_value;
+            let value = audio_data[i * audio_data.len() / bars] as f64;
+            let previous = self.previous_values[i];
+            let mut smoothed_value = (value + previous) / 2.0;
+            if let Some(max_step) = max_step {
+                let change = (smoothed_value - previous).clamp(-max_step, max_step);
+                smoothed_value = previous + change;
+            }
+            self.previous_values[i] = smoothed_value;
             
             let normalized = smoothed_value / 255.0;
-            let bar_height = normalized * (height as f64 * 0.15);
-            
-            let angle = (i as f64 / bars as f64) * PI * 2.0;
-            let x = angle.cos();
-            let y = angle.sin();
-            
-            ctx.set_fill_style(&JsValue::from_str(&format!("hsl({}, 100%, 50%)", (*hue + i as f64) % 360.0)));
+            let outer = radius + normalized * (self.height as f64 * 0.15);
             
-            ctx.begin_path();
-            ctx.move_to(x * radius, y * radius);
-            ctx.line_to(x * (radius + bar_height), y * (radius + bar_height));
-            
-            let next_angle = ((i + 1) as f64 / bars as f64) * PI * 2.0;
-            ctx.line_to(next_angle.cos() * (radius + bar_height), next_angle.sin() * (radius + bar_height));
-            ctx.line_to(next_angle.cos() * radius, next_angle.sin() * radius);
-            ctx.close_path();
-            ctx.fill();
+            let color = palette_index(self.hue + i as f64 * hue_spread);
+            let Ok(path) = self.batch.path(color) else {
+                continue;
+            };
+
+            let (x, y) = self.angles[i];
+            let (next_x, next_y) = self.angles[i + 1];
+            path.move_to(x * radius, y * radius);
+            path.line_to(x * outer, y * outer);
+            path.line_to(next_x * outer, next_y * outer);
+            path.line_to(next_x * radius, next_y * radius);
+            path.close_path();
         }
+
+        ctx.save();
+        let _ = ctx.rotate(self.rotation);
+        let folds = self.symmetry.folds();
+        if folds == 1 {
+            self.batch.fill_retained(ctx, &self.fill_palette);
+        } else {
+            let step = PI * 4.0 / folds as f64;
+            for _ in 0..folds / 2 {
+                self.batch.fill_retained(ctx, &self.fill_palette);
+                ctx.save();
+                let _ = ctx.scale(1.0, -1.0);
+                self.batch.fill_retained(ctx, &self.fill_palette);
+                ctx.restore();
+                let _ = ctx.rotate(step);
+            }
+        }
+        ctx.restore();
+        self.batch.clear();
     }
 
     fn draw_center_orb(&self, audio_data: &[u8]) {
         let ctx = &self.ctx;
-        let avg = audio_data.iter().map(|&x| x as f64).sum::<f64>() / audio_data.len() as f64;
+        let avg = if self.reduced_motion {
+            self.previous_values.iter().sum::<f64>() / self.previous_values.len() as f64
+        } else {
+            audio_data.iter().map(|&x| x as f64).sum::<f64>() / audio_data.len() as f64
+        };
+        if let Some(ref image) = self.center_image {
+            if image.complete() && image.natural_width() > 0 {
+                let bass = if self.reduced_motion || audio_data.is_empty() {
+                    avg
+                } else {
+                    let bins = (audio_data.len() / BASS_FRACTION).max(1);
+                    audio_data[..bins].iter().map(|&x| x as f64).sum::<f64>() / bins as f64
+                };
+                let radius = self.height as f64 * (0.2 + bass / 255.0 * 0.04);
+                self.draw_center_image(image, radius);
+                return;
+            }
+        }
+
         let radius = (avg / 255.0) * (self.height as f64 * 0.1) + 5.0;
         
-        ctx.set_fill_style(&JsValue::from_str(&format!("hsla({}, 100%, 50%, 0.8)", self.hue)));
+        ctx.set_fill_style(&self.glow_palette[palette_index(self.hue)]);
         
         ctx.begin_path();
         ctx.arc(0.0, 0.0, radius, 0.0, PI * 2.0).unwrap();
         ctx.fill();
     }
 
-    fn draw_particles(&self, audio_data: &[u8]) {
+    fn draw_center_image(&self, image: &HtmlImageElement, radius: f64) {
         let ctx = &self.ctx;
-        let bass = audio_data.iter().take(4).map(|&x| x as f64).sum::<f64>() / 4.0;
-
-        if bass > 200.0 {
-            for i in 0..20 {
-                let angle = (i as f64 / 20.0) * PI * 2.0;
-                let distance = bass / 255.0 * (self.height as f64 * 0.2);
-                let x = angle.cos() * distance;
-                let y = angle.sin() * distance;
-
-                ctx.set_fill_style(&JsValue::from_str(&format!(
-                    "hsla({}, 100%, 50%, 0.8)", 
-                    (self.hue + i as f64 * 3.0) % 360.0
-                )));
-                ctx.begin_path();
-                ctx.arc(x, y, 2.0, 0.0, PI * 2.0).unwrap();
-                ctx.fill();
+        ctx.save();
+        ctx.begin_path();
+        ctx.arc(0.0, 0.0, radius, 0.0, PI * 2.0).unwrap();
+        ctx.clip();
+
+        let width = image.natural_width() as f64;
+        let height = image.natural_height() as f64;
+        let scale = (radius * 2.0) / width.min(height);
+        let _ = ctx.draw_image_with_html_image_element_and_dw_and_dh(
+            image,
+            -width * scale / 2.0,
+            -height * scale / 2.0,
+            width * scale,
+            height * scale,
+        );
+        ctx.restore();
+
+        ctx.set_stroke_style(&self.glow_palette[palette_index(self.hue)]);
+        ctx.set_line_width(3.0);
+        ctx.begin_path();
+        ctx.arc(0.0, 0.0, radius, 0.0, PI * 2.0).unwrap();
+        ctx.stroke();
+    }
+
+    fn draw_particles(&mut self) {
+        let ctx = &self.ctx;
+
+        if self.burst > BURST_CUTOFF {
+            let height = self.height as f64;
+            let distance = height * (0.05 + (1.0 - self.burst) * 0.25);
+            let size = 1.0 + self.burst * 2.0;
+            for (i, &(x, y)) in self.particle_angles[..PARTICLE_COUNT].iter().enumerate() {
+                let Ok(path) = self.batch.path(palette_index(self.hue + i as f64 * 3.0)) else {
+                    continue;
+                };
+                path.move_to(x * distance + size, y * distance);
+                let _ = path.arc(x * distance, y * distance, size, 0.0, PI * 2.0);
             }
+            self.batch.fill(ctx, &self.glow_palette);
+            self.burst *= BURST_DECAY;
         }
     }
 }
+
+fn angle_table(steps: usize, span: f64) -> Vec<(f64, f64)> {
+    (0..=steps)
+        .map(|i| {
+            let angle = (i as f64 / steps as f64) * span;
+            (angle.cos(), angle.sin())
+        })
+        .collect()
+}
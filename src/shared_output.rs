@@ -0,0 +1,52 @@
+use js_sys::{Atomics, Int32Array, SharedArrayBuffer, Uint8Array};
+use wasm_bindgen::prelude::*;
+
+const SEQUENCE_INDEX: u32 = 0;
+const LENGTH_INDEX: u32 = 1;
+const HEADER_BYTES: u32 = 8;
+
+// Layout: [sequence: i32][bin count: i32][spectrum: u8 * bins]. The sequence
+// is a seqlock: it is odd while a frame is being written and even once it is
+// complete, so a reader copies only when it sees the same even value before
+// and after the copy. Waiters are notified on the sequence after each frame.
+pub struct SharedOutput {
+    header: Int32Array,
+    data: Uint8Array,
+}
+
+impl SharedOutput {
+    pub fn new(buffer: &SharedArrayBuffer, bins: usize) -> Result<SharedOutput, JsValue> {
+        let capacity = buffer.byte_length().saturating_sub(HEADER_BYTES);
+        let header = Int32Array::new_with_byte_offset_and_length(buffer, 0, 2);
+        let data = Uint8Array::new_with_byte_offset_and_length(buffer, HEADER_BYTES, capacity);
+        let output = SharedOutput { header, data };
+        output.check_capacity(bins)?;
+
+        Atomics::store(&output.header, SEQUENCE_INDEX, 0)?;
+        Atomics::store(&output.header, LENGTH_INDEX, 0)?;
+        Ok(output)
+    }
+
+    pub fn check_capacity(&self, bins: usize) -> Result<(), JsValue> {
+        if bins > self.data.length() as usize {
+            return Err(JsValue::from_str(&format!(
+                "Shared buffer needs at least {} bytes for {} bins",
+                HEADER_BYTES as usize + bins,
+                bins
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn write(&self, spectrum: &[u8]) -> Result<(), JsValue> {
+        self.check_capacity(spectrum.len())?;
+
+        let sequence = Atomics::load(&self.header, SEQUENCE_INDEX)?;
+        Atomics::store(&self.header, SEQUENCE_INDEX, sequence.wrapping_add(1))?;
+        self.data.subarray(0, spectrum.len() as u32).copy_from(spectrum);
+        Atomics::store(&self.header, LENGTH_INDEX, spectrum.len() as i32)?;
+        Atomics::store(&self.header, SEQUENCE_INDEX, sequence.wrapping_add(2))?;
+        Atomics::notify(&self.header, SEQUENCE_INDEX)?;
+        Ok(())
+    }
+}
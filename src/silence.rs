@@ -0,0 +1,81 @@
+use crate::dynamics::rms;
+use wasm_bindgen::prelude::*;
+use web_sys::AnalyserNode;
+
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub struct SilenceConfig {
+    pub threshold_db: f64,
+    pub duration_ms: f64,
+    pub pause_rendering: bool,
+}
+
+#[wasm_bindgen]
+impl SilenceConfig {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> SilenceConfig {
+        SilenceConfig {
+            threshold_db: -60.0,
+            duration_ms: 3000.0,
+            pause_rendering: false,
+        }
+    }
+}
+
+impl Default for SilenceConfig {
+    fn default() -> Self {
+        SilenceConfig::new()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SilenceChange {
+    Silence,
+    Signal,
+}
+
+pub struct SilenceDetector {
+    config: SilenceConfig,
+    samples: Vec<f32>,
+    quiet_since: Option<f64>,
+    silent: bool,
+}
+
+impl SilenceDetector {
+    pub fn new(config: SilenceConfig) -> SilenceDetector {
+        SilenceDetector {
+            config,
+            samples: Vec::new(),
+            quiet_since: None,
+            silent: false,
+        }
+    }
+
+    pub fn is_silent(&self) -> bool {
+        self.silent
+    }
+
+    pub fn pauses_rendering(&self) -> bool {
+        self.silent && self.config.pause_rendering
+    }
+
+    pub fn process(&mut self, analyser: &AnalyserNode, now: f64) -> Option<SilenceChange> {
+        let level_db = 20.0 * rms(analyser, &mut self.samples).max(1e-10).log10();
+
+        if level_db >= self.config.threshold_db {
+            self.quiet_since = None;
+            if self.silent {
+                self.silent = false;
+                return Some(SilenceChange::Signal);
+            }
+            return None;
+        }
+
+        let since = *self.quiet_since.get_or_insert(now);
+        if !self.silent && now - since >= self.config.duration_ms.max(0.0) {
+            self.silent = true;
+            return Some(SilenceChange::Silence);
+        }
+        None
+    }
+}
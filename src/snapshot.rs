@@ -0,0 +1,50 @@
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Blob, CanvasRenderingContext2d, HtmlCanvasElement};
+
+pub async fn capture(canvas: &HtmlCanvasElement, scale: f64) -> Result<Blob, JsValue> {
+    if scale <= 0.0 || !scale.is_finite() {
+        return Err(JsValue::from_str("Snapshot scale must be a positive number"));
+    }
+
+    let target = if scale == 1.0 {
+        canvas.clone()
+    } else {
+        upscale(canvas, scale)?
+    };
+
+    let mut to_blob_result = Ok(());
+    let promise = js_sys::Promise::new(&mut |resolve, _reject| {
+        to_blob_result = target.to_blob_with_type(&resolve, "image/png");
+    });
+    to_blob_result?;
+
+    let blob = JsFuture::from(promise).await?;
+    if blob.is_null() {
+        return Err(JsValue::from_str("Canvas could not be encoded as PNG"));
+    }
+    blob.dyn_into()
+}
+
+fn upscale(canvas: &HtmlCanvasElement, scale: f64) -> Result<HtmlCanvasElement, JsValue> {
+    let document = web_sys::window()
+        .and_then(|window| window.document())
+        .ok_or_else(|| JsValue::from_str("No document found"))?;
+
+    let width = (canvas.width() as f64 * scale).round();
+    let height = (canvas.height() as f64 * scale).round();
+
+    let target: HtmlCanvasElement = document.create_element("canvas")?.dyn_into()?;
+    target.set_width(width as u32);
+    target.set_height(height as u32);
+
+    let ctx = target
+        .get_context("2d")?
+        .ok_or_else(|| JsValue::from_str("Failed to get 2D context"))?
+        .dyn_into::<CanvasRenderingContext2d>()?;
+    ctx.set_image_smoothing_enabled(true);
+    ctx.draw_image_with_html_canvas_element_and_dw_and_dh(canvas, 0.0, 0.0, width, height)?;
+
+    Ok(target)
+}
@@ -0,0 +1,462 @@
+use crate::events::EventCallbacks;
+use crate::icy::IcyDemuxer;
+use crate::id3::Id3Reader;
+use crate::looping::SharedLoop;
+use crate::metadata::SharedMetadata;
+use crate::request::RequestOptions;
+use crate::retry::{Backoff, RetryConfig};
+use crate::{log, STREAM_MIME_TYPE};
+use futures::channel::mpsc;
+use futures::StreamExt;
+use js_sys::Uint8Array;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{HtmlMediaElement, MediaSource, MediaSourceReadyState, Response, SourceBuffer, Window};
+
+pub const LIVE_RETENTION_SECONDS: f64 = 30.0;
+
+const QUOTA_RETENTION_SECONDS: f64 = 5.0;
+const QUOTA_RETRY_DELAY_MS: i32 = 500;
+const QUOTA_MAX_RETRIES: u32 = 10;
+
+#[derive(Clone, Default)]
+pub struct StreamOptions {
+    pub live: bool,
+    pub icy_metadata: bool,
+    pub retention: Option<f64>,
+    pub looping: SharedLoop,
+    pub request: RequestOptions,
+    pub retry: Option<RetryConfig>,
+}
+
+pub async fn pump_stream(
+    window: Window,
+    media_source: MediaSource,
+    audio_element: HtmlMediaElement,
+    url: String,
+    options: StreamOptions,
+    events: EventCallbacks,
+    metadata: SharedMetadata,
+) -> Result<(), JsValue> {
+    log("MediaSource opened, creating SourceBuffer");
+    let source_buffer = media_source.add_source_buffer(STREAM_MIME_TYPE)?;
+
+    let retention = options
+        .retention
+        .or(options.live.then_some(LIVE_RETENTION_SECONDS));
+    // Live and ICY streams cannot resume at a byte offset, so they just reconnect.
+    let resumable = !options.live && !options.icy_metadata;
+    let mut backoff = Backoff::new(options.retry);
+    let mut received: u64 = 0;
+
+    let mut audio = Vec::new();
+    let mut id3 = Id3Reader::default();
+
+    'connection: loop {
+        let offset = if resumable { received } else { 0 };
+        let (reader, mut skip, mut demuxer) =
+            match connect(&window, &url, &options, offset).await {
+                Ok(connection) => connection,
+                Err(Failure::Fatal(error)) => return Err(error),
+                Err(Failure::Retry(error)) => {
+                    backoff.wait(&window, &events, error).await?;
+                    continue;
+                }
+            };
+
+        loop {
+            let chunk = match JsFuture::from(reader.read()).await {
+                Ok(chunk) => chunk,
+                Err(error) => {
+                    if is_closed(&media_source) {
+                        break 'connection;
+                    }
+                    backoff.wait(&window, &events, error).await?;
+                    continue 'connection;
+                }
+            };
+
+            if is_closed(&media_source) {
+                log("MediaSource closed, stopping stream");
+                let _ = reader.cancel();
+                break 'connection;
+            }
+
+            let obj = js_sys::Object::from(chunk);
+
+            let done = js_sys::Reflect::get(&obj, &"done".into())?
+                .as_bool()
+                .unwrap_or(false);
+
+            if done {
+                if options.live {
+                    log("Live stream closed by server");
+                } else {
+                    log("All data has been read, ending stream");
+                    media_source.end_of_stream()?;
+                }
+                break 'connection;
+            }
+
+            let Ok(value) = js_sys::Reflect::get(&obj, &"value".into()) else {
+                continue;
+            };
+            let mut bytes = Uint8Array::new(&value).to_vec();
+            if skip > 0 {
+                // The server ignored our Range header and restarted from byte 0.
+                let dropped = skip.min(bytes.len() as u64);
+                bytes.drain(..dropped as usize);
+                skip -= dropped;
+                if bytes.is_empty() {
+                    continue;
+                }
+            }
+            received += bytes.len() as u64;
+
+            match demuxer {
+                Some(ref mut demuxer) => {
+                    audio.clear();
+                    if let Some(title) = demuxer.push(&bytes, &mut audio) {
+                        log(&format!("Now playing: {}", title));
+                        metadata.set_title(&title, &events)?;
+                    }
+                    if audio.is_empty() {
+                        continue;
+                    }
+                }
+                None => audio = bytes,
+            }
+
+            if let Some(tag) = id3.push(&audio) {
+                metadata.apply_tag(tag, &events)?;
+            }
+
+            loop {
+                match append(&source_buffer, &media_source, &audio_element, &mut audio, retention, &options.looping).await {
+                    Ok(true) => break,
+                    Ok(false) => {
+                        log("MediaSource closed, stopping stream");
+                        let _ = reader.cancel();
+                        break 'connection;
+                    }
+                    Err(error) => backoff.wait(&window, &events, error).await?,
+                }
+            }
+            backoff.succeeded(&events);
+            log("Successfully appended buffer");
+        }
+    }
+
+    Ok(())
+}
+
+enum Failure {
+    Retry(JsValue),
+    Fatal(JsValue),
+}
+
+async fn connect(
+    window: &Window,
+    url: &str,
+    options: &StreamOptions,
+    offset: u64,
+) -> Result<(web_sys::ReadableStreamDefaultReader, u64, Option<IcyDemuxer>), Failure> {
+    let range = format!("bytes={}-", offset);
+    let mut headers = Vec::new();
+    if options.icy_metadata {
+        headers.push(("Icy-MetaData", "1"));
+    }
+    if offset > 0 {
+        headers.push(("Range", range.as_str()));
+    }
+
+    let response = options
+        .request
+        .fetch(window, url, &headers)
+        .await
+        .map_err(Failure::Retry)?;
+
+    if !response.ok() {
+        let error = JsValue::from_str("Failed to fetch audio file");
+        let status = response.status();
+        return Err(if status >= 500 || status == 408 || status == 429 {
+            Failure::Retry(error)
+        } else {
+            Failure::Fatal(error)
+        });
+    }
+
+    let skip = if offset > 0 && response.status() != 206 { offset } else { 0 };
+
+    let demuxer = response
+        .headers()
+        .get("icy-metaint")
+        .map_err(Failure::Fatal)?
+        .and_then(|value| value.trim().parse::<usize>().ok())
+        .filter(|&metaint| metaint > 0)
+        .map(IcyDemuxer::new);
+
+    let body = response
+        .body()
+        .ok_or_else(|| Failure::Fatal(JsValue::from_str("No response body")))?;
+    let reader = body
+        .get_reader()
+        .dyn_into::<web_sys::ReadableStreamDefaultReader>()
+        .map_err(|_| Failure::Fatal(JsValue::from_str("Response body is not readable")))?;
+
+    Ok((reader, skip, demuxer))
+}
+
+pub async fn pump_websocket(
+    window: Window,
+    media_source: MediaSource,
+    audio_element: HtmlMediaElement,
+    url: String,
+    options: StreamOptions,
+    events: EventCallbacks,
+) -> Result<(), JsValue> {
+    log("Opening WebSocket audio stream");
+    let source_buffer = media_source.add_source_buffer(STREAM_MIME_TYPE)?;
+    let retention = options.retention.unwrap_or(LIVE_RETENTION_SECONDS);
+    let mut backoff = Backoff::new(options.retry);
+
+    loop {
+        let mut socket = Socket::open(&url)?;
+        let mut received = false;
+
+        while let Some(mut chunk) = socket.next().await {
+            if !received {
+                received = true;
+                backoff.succeeded(&events);
+            }
+            if is_closed(&media_source)
+                || !append(&source_buffer, &media_source, &audio_element, &mut chunk, Some(retention), &options.looping).await?
+            {
+                log("MediaSource closed, closing WebSocket");
+                return Ok(());
+            }
+        }
+
+        match socket.dropped_code() {
+            Some(_) if is_closed(&media_source) => return Ok(()),
+            Some(code) => {
+                let error = JsValue::from_str(&format!("WebSocket dropped with code {}", code));
+                backoff.wait(&window, &events, error).await?;
+            }
+            None => {
+                log("WebSocket closed, ending stream");
+                return media_source.end_of_stream();
+            }
+        }
+    }
+}
+
+struct Socket {
+    socket: web_sys::WebSocket,
+    receiver: mpsc::UnboundedReceiver<Vec<u8>>,
+    // Close code of a connection that ended without a clean close handshake.
+    dropped: Rc<Cell<Option<u16>>>,
+    _on_message: Closure<dyn FnMut(web_sys::MessageEvent)>,
+    _on_close: Closure<dyn FnMut(web_sys::CloseEvent)>,
+}
+
+impl Socket {
+    fn open(url: &str) -> Result<Socket, JsValue> {
+        let socket = web_sys::WebSocket::new(url)?;
+        socket.set_binary_type(web_sys::BinaryType::Arraybuffer);
+
+        let (sender, receiver) = mpsc::unbounded::<Vec<u8>>();
+        let sender = Rc::new(RefCell::new(Some(sender)));
+        let dropped = Rc::new(Cell::new(None));
+
+        let on_message = {
+            let sender = sender.clone();
+            Closure::wrap(Box::new(move |event: web_sys::MessageEvent| {
+                let data = event.data();
+                if data.is_instance_of::<js_sys::ArrayBuffer>() {
+                    if let Some(ref sender) = *sender.borrow() {
+                        let _ = sender.unbounded_send(Uint8Array::new(&data).to_vec());
+                    }
+                }
+            }) as Box<dyn FnMut(web_sys::MessageEvent)>)
+        };
+        socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+
+        let on_close = {
+            let dropped = dropped.clone();
+            Closure::wrap(Box::new(move |event: web_sys::CloseEvent| {
+                if !event.was_clean() {
+                    dropped.set(Some(event.code()));
+                }
+                sender.borrow_mut().take();
+            }) as Box<dyn FnMut(web_sys::CloseEvent)>)
+        };
+        socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+
+        Ok(Socket {
+            socket,
+            receiver,
+            dropped,
+            _on_message: on_message,
+            _on_close: on_close,
+        })
+    }
+
+    async fn next(&mut self) -> Option<Vec<u8>> {
+        self.receiver.next().await
+    }
+
+    fn dropped_code(&self) -> Option<u16> {
+        self.dropped.get()
+    }
+}
+
+impl Drop for Socket {
+    fn drop(&mut self) {
+        self.socket.set_onmessage(None);
+        self.socket.set_onclose(None);
+        let _ = self.socket.close();
+    }
+}
+
+pub fn is_closed(media_source: &MediaSource) -> bool {
+    media_source.ready_state() == MediaSourceReadyState::Closed
+}
+
+pub async fn fetch_ok(
+    window: &Window,
+    url: &str,
+    request: &RequestOptions,
+) -> Result<Response, JsValue> {
+    let response = request.fetch(window, url, &[]).await?;
+
+    if !response.ok() {
+        return Err(JsValue::from_str(&format!("Failed to fetch {}", url)));
+    }
+
+    Ok(response)
+}
+
+pub async fn sleep(window: &Window, ms: i32) -> Result<(), JsValue> {
+    let promise = js_sys::Promise::new(&mut |resolve, _| {
+        let _ = window.set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, ms);
+    });
+    JsFuture::from(promise).await.map(|_| ())
+}
+
+pub async fn append(
+    source_buffer: &SourceBuffer,
+    media_source: &MediaSource,
+    audio_element: &HtmlMediaElement,
+    data: &mut [u8],
+    retention: Option<f64>,
+    looping: &SharedLoop,
+) -> Result<bool, JsValue> {
+    let mut attempts = 0;
+    loop {
+        match source_buffer.append_buffer_with_u8_array(data) {
+            Ok(()) => break,
+            Err(error) if is_quota_exceeded(&error) => {
+                if is_closed(media_source) {
+                    return Ok(false);
+                }
+                attempts += 1;
+                if attempts > QUOTA_MAX_RETRIES {
+                    return Err(JsValue::from_str(
+                        "SourceBuffer stayed full after evicting played audio",
+                    ));
+                }
+                log("SourceBuffer is full, evicting played audio before retrying");
+                let retention = retention.map_or(QUOTA_RETENTION_SECONDS, |r| r.min(QUOTA_RETENTION_SECONDS));
+                let keep_from = looping.restart_at();
+                evict_behind(source_buffer, audio_element.current_time(), retention, keep_from).await?;
+
+                let window = web_sys::window().ok_or_else(|| JsValue::from_str("No window found"))?;
+                sleep(&window, QUOTA_RETRY_DELAY_MS).await?;
+            }
+            Err(error) => return Err(error),
+        }
+    }
+    wait_for_updateend(source_buffer).await?;
+
+    if let Some(retention) = retention {
+        // Keep everything a loop may jump back to.
+        let keep_from = looping.restart_at();
+        evict_behind(source_buffer, audio_element.current_time(), retention, keep_from).await?;
+    }
+
+    Ok(true)
+}
+
+fn is_quota_exceeded(error: &JsValue) -> bool {
+    js_sys::Reflect::get(error, &"name".into())
+        .ok()
+        .and_then(|name| name.as_string())
+        .is_some_and(|name| name == "QuotaExceededError")
+}
+
+pub async fn evict_behind(
+    source_buffer: &SourceBuffer,
+    current_time: f64,
+    retention: f64,
+    keep_from: Option<f64>,
+) -> Result<(), JsValue> {
+    let buffered = source_buffer.buffered()?;
+    if buffered.length() == 0 {
+        return Ok(());
+    }
+
+    let start = buffered.start(0)?;
+    let cutoff = keep_from.map_or(current_time - retention, |keep_from| {
+        (current_time - retention).min(keep_from)
+    });
+    if cutoff > start {
+        source_buffer.remove(start, cutoff)?;
+        wait_for_updateend(source_buffer).await?;
+    }
+
+    Ok(())
+}
+
+pub async fn wait_for_updateend(source_buffer: &SourceBuffer) -> Result<(), JsValue> {
+    use futures::channel::oneshot;
+    use wasm_bindgen::closure::Closure;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    struct UpdateEndHandler {
+        _closure: Closure<dyn FnMut()>,
+    }
+
+    impl UpdateEndHandler {
+        fn new(source_buffer: &SourceBuffer) -> (Rc<RefCell<Option<Self>>>, oneshot::Receiver<()>) {
+            let (sender, receiver) = oneshot::channel::<()>();
+            let handler = Rc::new(RefCell::new(None));
+
+            let handler_clone = handler.clone();
+            let sender = Rc::new(RefCell::new(Some(sender)));
+
+            let closure = Closure::wrap(Box::new(move || {
+                if let Some(sender) = sender.borrow_mut().take() {
+                    let _ = sender.send(());
+                }
+
+                handler_clone.borrow_mut().take();
+            }) as Box<dyn FnMut()>);
+
+            source_buffer.set_onupdateend(Some(closure.as_ref().unchecked_ref()));
+
+            *handler.borrow_mut() = Some(UpdateEndHandler { _closure: closure });
+
+            (handler, receiver)
+        }
+    }
+
+    let (_handler, receiver) = UpdateEndHandler::new(source_buffer);
+
+    receiver.await.map_err(|_| JsValue::from_str("Failed to receive updateend event"))
+}
@@ -0,0 +1,162 @@
+use crate::document;
+use std::cell::Cell;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::CanvasRenderingContext2d;
+
+const LEVEL_SMOOTHING: f64 = 0.8;
+
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct TextConfig {
+    #[wasm_bindgen(getter_with_clone)]
+    pub font_family: String,
+    #[wasm_bindgen(getter_with_clone)]
+    pub font_weight: String,
+    #[wasm_bindgen(getter_with_clone)]
+    pub color: String,
+    #[wasm_bindgen(getter_with_clone)]
+    pub glow_color: String,
+    pub size: f64,
+    pub x: f64,
+    pub y: f64,
+    pub max_width: f64,
+    pub reactivity: f64,
+    pub glow: f64,
+}
+
+#[wasm_bindgen]
+impl TextConfig {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> TextConfig {
+        TextConfig {
+            font_family: "sans-serif".to_string(),
+            font_weight: "bold".to_string(),
+            color: "#ffffff".to_string(),
+            glow_color: "rgba(255, 255, 255, 0.8)".to_string(),
+            size: 0.08,
+            x: 0.5,
+            y: 0.85,
+            max_width: 0.9,
+            reactivity: 0.15,
+            glow: 20.0,
+        }
+    }
+}
+
+impl Default for TextConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct Layout {
+    width: u32,
+    height: u32,
+    font: String,
+}
+
+pub struct TextOverlay {
+    text: String,
+    config: TextConfig,
+    layout: Option<Layout>,
+    font_loaded: Rc<Cell<bool>>,
+    level: f64,
+}
+
+impl TextOverlay {
+    pub fn new(text: &str, config: &TextConfig) -> Result<TextOverlay, JsValue> {
+        let font_loaded = Rc::new(Cell::new(false));
+        let load = document()?
+            .fonts()
+            .load_with_text(&font(config, 16.0), text);
+        {
+            let font_loaded = font_loaded.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                if JsFuture::from(load).await.is_ok() {
+                    font_loaded.set(true);
+                }
+            });
+        }
+
+        Ok(TextOverlay {
+            text: text.to_string(),
+            config: config.clone(),
+            layout: None,
+            font_loaded,
+            level: 0.0,
+        })
+    }
+
+    pub fn draw(
+        &mut self,
+        ctx: &CanvasRenderingContext2d,
+        width: u32,
+        height: u32,
+        audio_data: &[u8],
+        reduced_motion: bool,
+    ) {
+        if self.text.is_empty() || width == 0 || height == 0 {
+            return;
+        }
+
+        if self.font_loaded.replace(false) {
+            self.layout = None;
+        }
+        let stale = !matches!(self.layout, Some(ref layout) if layout.width == width && layout.height == height);
+        if stale {
+            self.layout = Some(self.layout(ctx, width, height));
+        }
+
+        let current = if audio_data.is_empty() {
+            0.0
+        } else {
+            audio_data.iter().map(|&x| x as f64).sum::<f64>() / (audio_data.len() as f64 * 255.0)
+        };
+        self.level = LEVEL_SMOOTHING * self.level + (1.0 - LEVEL_SMOOTHING) * current;
+        let scale = if reduced_motion {
+            1.0
+        } else {
+            1.0 + self.config.reactivity * self.level
+        };
+
+        let Some(ref layout) = self.layout else {
+            return;
+        };
+        ctx.save();
+        ctx.set_font(&layout.font);
+        ctx.set_text_align("center");
+        ctx.set_text_baseline("middle");
+        ctx.set_shadow_color(&self.config.glow_color);
+        ctx.set_shadow_blur(self.config.glow * self.level);
+        ctx.set_fill_style(&JsValue::from_str(&self.config.color));
+        let _ = ctx.translate(self.config.x * width as f64, self.config.y * height as f64);
+        let _ = ctx.scale(scale, scale);
+        let _ = ctx.fill_text(&self.text, 0.0, 0.0);
+        ctx.restore();
+    }
+
+    fn layout(&self, ctx: &CanvasRenderingContext2d, width: u32, height: u32) -> Layout {
+        let mut size = (self.config.size * height as f64).max(1.0);
+        ctx.save();
+        ctx.set_font(&font(&self.config, size));
+        let measured = ctx.measure_text(&self.text).map(|metrics| metrics.width()).unwrap_or(0.0);
+        ctx.restore();
+
+        let max_width = self.config.max_width * width as f64;
+        if measured > max_width && measured > 0.0 {
+            size = (size * max_width / measured).max(1.0);
+        }
+
+        Layout {
+            width,
+            height,
+            font: font(&self.config, size),
+        }
+    }
+}
+
+fn font(config: &TextConfig, size: f64) -> String {
+    format!("{} {}px {}", config.font_weight, size, config.font_family)
+}
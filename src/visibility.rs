@@ -0,0 +1,65 @@
+use crate::{document, log, pause_playback, resume_playback};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{AudioContext, HtmlMediaElement};
+
+#[derive(Default)]
+struct State {
+    hidden: Cell<bool>,
+    pause_audio: Cell<bool>,
+    auto_paused: Cell<bool>,
+}
+
+pub struct PageVisibility {
+    state: Rc<State>,
+    _listener: Closure<dyn FnMut()>,
+}
+
+impl PageVisibility {
+    pub fn install(
+        audio_element: Rc<RefCell<Option<HtmlMediaElement>>>,
+        context: AudioContext,
+    ) -> Result<PageVisibility, JsValue> {
+        let document = document()?;
+        let state = Rc::new(State::default());
+        state.hidden.set(document.hidden());
+
+        let listener = {
+            let state = state.clone();
+            let document = document.clone();
+            Closure::wrap(Box::new(move || {
+                let hidden = document.hidden();
+                state.hidden.set(hidden);
+
+                if hidden && state.pause_audio.get() {
+                    if pause_playback(&audio_element.borrow(), &context).is_ok() {
+                        log("Page hidden, pausing audio");
+                        state.auto_paused.set(true);
+                    }
+                } else if !hidden && state.auto_paused.replace(false) {
+                    log("Page visible again, resuming audio");
+                    let _ = resume_playback(&audio_element.borrow(), &context);
+                }
+            }) as Box<dyn FnMut()>)
+        };
+        document.add_event_listener_with_callback(
+            "visibilitychange",
+            listener.as_ref().unchecked_ref(),
+        )?;
+
+        Ok(PageVisibility {
+            state,
+            _listener: listener,
+        })
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        self.state.hidden.get()
+    }
+
+    pub fn set_pause_audio(&self, enabled: bool) {
+        self.state.pause_audio.set(enabled);
+    }
+}
@@ -0,0 +1,52 @@
+use wasm_bindgen::prelude::*;
+use web_sys::AudioBuffer;
+
+const RESOLUTION: usize = 8192;
+
+pub struct Waveform {
+    minima: Vec<f32>,
+    maxima: Vec<f32>,
+    duration: f64,
+}
+
+impl Waveform {
+    pub fn from_buffer(buffer: &AudioBuffer) -> Result<Waveform, JsValue> {
+        let length = buffer.length() as usize;
+        let buckets = RESOLUTION.min(length).max(1);
+        let mut minima = vec![0.0f32; buckets];
+        let mut maxima = vec![0.0f32; buckets];
+
+        for channel in 0..buffer.number_of_channels() {
+            let data = buffer.get_channel_data(channel)?;
+            for (index, &sample) in data.iter().enumerate() {
+                let bucket = (index as u64 * buckets as u64 / length as u64) as usize;
+                minima[bucket] = minima[bucket].min(sample);
+                maxima[bucket] = maxima[bucket].max(sample);
+            }
+        }
+
+        Ok(Waveform {
+            minima,
+            maxima,
+            duration: buffer.duration(),
+        })
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.duration
+    }
+
+    pub fn peaks(&self, width: usize) -> Vec<f32> {
+        let buckets = self.minima.len();
+        let mut peaks = Vec::with_capacity(width * 2);
+        for column in 0..width {
+            let start = (column * buckets / width).min(buckets - 1);
+            let end = ((column + 1) * buckets / width).clamp(start + 1, buckets);
+            let min = self.minima[start..end].iter().copied().fold(0.0, f32::min);
+            let max = self.maxima[start..end].iter().copied().fold(0.0, f32::max);
+            peaks.push(min);
+            peaks.push(max);
+        }
+        peaks
+    }
+}
@@ -0,0 +1,141 @@
+use crate::background::Background;
+use crate::waveform::Waveform;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, Path2d};
+
+const LEVEL_SMOOTHING: f64 = 0.8;
+const MAX_GLOW: f64 = 16.0;
+const UNPLAYED_COLOR: &str = "rgba(255, 255, 255, 0.35)";
+const PLAYHEAD_COLOR: &str = "#ffffff";
+
+pub struct WaveformStyle {
+    ctx: CanvasRenderingContext2d,
+    width: u32,
+    height: u32,
+    waveform: Option<Rc<Waveform>>,
+    peaks: Vec<f32>,
+    progress: f64,
+    level: f64,
+    hue: f64,
+    key_hue: Option<f64>,
+    reduced_motion: bool,
+    background: Option<Background>,
+}
+
+impl WaveformStyle {
+    pub fn new(canvas: HtmlCanvasElement) -> Result<WaveformStyle, JsValue> {
+        let ctx = canvas
+            .get_context("2d")?
+            .ok_or_else(|| JsValue::from_str("Failed to get 2D context"))?
+            .dyn_into::<CanvasRenderingContext2d>()?;
+
+        Ok(WaveformStyle {
+            ctx,
+            width: canvas.width(),
+            height: canvas.height(),
+            waveform: None,
+            peaks: Vec::new(),
+            progress: 0.0,
+            level: 0.0,
+            hue: 200.0,
+            key_hue: None,
+            reduced_motion: false,
+            background: None,
+        })
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if width != self.width {
+            self.peaks.clear();
+        }
+        self.width = width;
+        self.height = height;
+    }
+
+    pub fn set_context(&mut self, ctx: CanvasRenderingContext2d) {
+        self.ctx = ctx;
+    }
+
+    pub fn set_background(&mut self, background: Option<Background>) {
+        self.background = background;
+    }
+
+    pub fn set_reduced_motion(&mut self, enabled: bool) {
+        self.reduced_motion = enabled;
+    }
+
+    pub fn set_key_hue(&mut self, hue: Option<f64>) {
+        self.key_hue = hue;
+    }
+
+    pub fn set_waveform(&mut self, waveform: Option<Rc<Waveform>>) {
+        self.waveform = waveform;
+        self.peaks.clear();
+    }
+
+    pub fn set_progress(&mut self, progress: f64) {
+        self.progress = if progress.is_finite() {
+            progress.clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+    }
+
+    pub fn draw(&mut self, audio_data: &[u8]) {
+        let ctx = &self.ctx;
+        let (width, height) = (self.width as f64, self.height as f64);
+        match self.background {
+            Some(ref background) => background.paint(ctx),
+            None => Background::transparent().paint(ctx),
+        }
+
+        let current = if audio_data.is_empty() {
+            0.0
+        } else {
+            audio_data.iter().map(|&x| x as f64).sum::<f64>() / (audio_data.len() as f64 * 255.0)
+        };
+        self.level = LEVEL_SMOOTHING * self.level + (1.0 - LEVEL_SMOOTHING) * current;
+        if let Some(hue) = self.key_hue {
+            self.hue = hue;
+        }
+
+        let middle = height / 2.0;
+        let (Ok(played), Ok(unplayed)) = (Path2d::new(), Path2d::new()) else {
+            return;
+        };
+        match self.waveform {
+            Some(ref waveform) => {
+                if self.peaks.len() != self.width as usize * 2 {
+                    self.peaks = waveform.peaks(self.width as usize);
+                }
+                let playhead = self.progress * width;
+                for (column, pair) in self.peaks.chunks_exact(2).enumerate() {
+                    let x = column as f64;
+                    let top = middle - pair[1] as f64 * middle;
+                    let bottom = middle - pair[0] as f64 * middle;
+                    let path = if x < playhead { &played } else { &unplayed };
+                    path.rect(x, top, 1.0, (bottom - top).max(1.0));
+                }
+            }
+            None => unplayed.rect(0.0, middle - 0.5, width, 1.0),
+        }
+
+        ctx.set_fill_style(&JsValue::from_str(UNPLAYED_COLOR));
+        ctx.fill_with_path_2d(&unplayed);
+        ctx.set_fill_style(&JsValue::from_str(&format!("hsl({}, 80%, 60%)", self.hue as i32)));
+        ctx.fill_with_path_2d(&played);
+
+        if self.waveform.is_some() {
+            ctx.save();
+            if !self.reduced_motion {
+                ctx.set_shadow_color(PLAYHEAD_COLOR);
+                ctx.set_shadow_blur(MAX_GLOW * self.level);
+            }
+            ctx.set_fill_style(&JsValue::from_str(PLAYHEAD_COLOR));
+            ctx.fill_rect((self.progress * width - 1.0).max(0.0), 0.0, 2.0, height);
+            ctx.restore();
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use serde::{Deserialize, Serialize};
+use tsify_next::Tsify;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize, Tsify)]
+#[serde(rename = "WeightingName", rename_all = "lowercase")]
+pub enum Weighting {
+    None,
+    A,
+    C,
+}
+
+impl Weighting {
+    // IEC 61672 curves, normalised to 0 dB at 1 kHz.
+    fn gain_db(self, frequency: f64) -> f64 {
+        let f2 = frequency * frequency;
+        let (r1, r2) = (20.6f64.powi(2), 107.7f64.powi(2));
+        let (r3, r4) = (737.9f64.powi(2), 12194f64.powi(2));
+        match self {
+            Weighting::None => 0.0,
+            Weighting::A => {
+                let gain = r4 * f2 * f2 / ((f2 + r1) * ((f2 + r2) * (f2 + r3)).sqrt() * (f2 + r4));
+                20.0 * gain.log10() + 2.0
+            }
+            Weighting::C => {
+                let gain = r4 * f2 / ((f2 + r1) * (f2 + r4));
+                20.0 * gain.log10() + 0.06
+            }
+        }
+    }
+}
+
+pub struct WeightingCurve {
+    weighting: Weighting,
+    key: (usize, u64, u64),
+    offsets: Vec<f64>,
+}
+
+impl WeightingCurve {
+    pub fn new() -> WeightingCurve {
+        WeightingCurve {
+            weighting: Weighting::None,
+            key: (0, 0, 0),
+            offsets: Vec::new(),
+        }
+    }
+
+    pub fn weighting(&self) -> Weighting {
+        self.weighting
+    }
+
+    pub fn set_weighting(&mut self, weighting: Weighting) {
+        self.weighting = weighting;
+        self.offsets.clear();
+    }
+
+    // Byte spectra are linear in dB between the analyser's min and max
+    // decibels, so the weighting is a per-bin additive offset.
+    pub fn apply(&mut self, data: &mut [u8], nyquist: f64, min_db: f64, max_db: f64) {
+        if self.weighting == Weighting::None || data.is_empty() {
+            return;
+        }
+        let key = (data.len(), nyquist.to_bits(), (max_db - min_db).to_bits());
+        if self.offsets.len() != data.len() || self.key != key {
+            let scale = 255.0 / (max_db - min_db).max(f64::EPSILON);
+            let bin_width = nyquist / data.len() as f64;
+            self.offsets = (0..data.len())
+                .map(|bin| self.weighting.gain_db(bin as f64 * bin_width) * scale)
+                .collect();
+            self.key = key;
+        }
+        for (value, offset) in data.iter_mut().zip(&self.offsets) {
+            *value = (*value as f64 + offset).clamp(0.0, 255.0) as u8;
+        }
+    }
+}
+
+impl Default for WeightingCurve {
+    fn default() -> Self {
+        WeightingCurve::new()
+    }
+}
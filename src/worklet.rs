@@ -0,0 +1,218 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{AnalyserNode, AudioContext, AudioWorkletNode, AudioWorkletNodeOptions, MessageEvent};
+
+const PROCESSOR_NAME: &str = "audio-visualizer-analyser";
+const HOP_SIZE: u32 = 512;
+const BAND_COUNT: u32 = 8;
+
+const PROCESSOR_SOURCE: &str = r#"
+class AudioVisualizerAnalyser extends AudioWorkletProcessor {
+  constructor(options) {
+    super();
+    const config = options.processorOptions || {};
+    this.size = config.fftSize || 2048;
+    this.hop = config.hopSize || 512;
+    this.smoothing = config.smoothing ?? 0.8;
+    this.minDecibels = config.minDecibels ?? -100;
+    this.maxDecibels = config.maxDecibels ?? -30;
+    this.bandCount = config.bands || 8;
+    this.ring = new Float32Array(this.size);
+    this.real = new Float32Array(this.size);
+    this.imag = new Float32Array(this.size);
+    this.smoothed = new Float32Array(this.size / 2);
+    this.window = new Float32Array(this.size);
+    for (let i = 0; i < this.size; i++) {
+      const a = (2 * Math.PI * i) / this.size;
+      this.window[i] = 0.42 - 0.5 * Math.cos(a) + 0.08 * Math.cos(2 * a);
+    }
+    this.write = 0;
+    this.pending = 0;
+  }
+
+  process(inputs) {
+    const input = inputs[0];
+    if (input && input.length > 0) {
+      const frames = input[0].length;
+      for (let i = 0; i < frames; i++) {
+        let sum = 0;
+        for (let c = 0; c < input.length; c++) sum += input[c][i];
+        this.ring[this.write] = sum / input.length;
+        this.write = (this.write + 1) % this.size;
+      }
+      this.pending += frames;
+      if (this.pending >= this.hop) {
+        this.pending = 0;
+        this.analyse();
+      }
+    }
+    return true;
+  }
+
+  analyse() {
+    const n = this.size, re = this.real, im = this.imag;
+    for (let i = 0; i < n; i++) {
+      re[i] = this.ring[(this.write + i) % n] * this.window[i];
+      im[i] = 0;
+    }
+    for (let i = 1, j = 0; i < n; i++) {
+      let bit = n >> 1;
+      for (; j & bit; bit >>= 1) j ^= bit;
+      j ^= bit;
+      if (i < j) {
+        [re[i], re[j]] = [re[j], re[i]];
+        [im[i], im[j]] = [im[j], im[i]];
+      }
+    }
+    for (let len = 2; len <= n; len <<= 1) {
+      const angle = (-2 * Math.PI) / len, wr = Math.cos(angle), wi = Math.sin(angle);
+      for (let i = 0; i < n; i += len) {
+        let cr = 1, ci = 0;
+        for (let k = 0; k < len / 2; k++) {
+          const a = i + k, b = a + len / 2;
+          const tr = re[b] * cr - im[b] * ci, ti = re[b] * ci + im[b] * cr;
+          re[b] = re[a] - tr;
+          im[b] = im[a] - ti;
+          re[a] += tr;
+          im[a] += ti;
+          const next = cr * wr - ci * wi;
+          ci = cr * wi + ci * wr;
+          cr = next;
+        }
+      }
+    }
+
+    const bins = n / 2, frequency = new Uint8Array(bins);
+    const range = this.maxDecibels - this.minDecibels;
+    for (let k = 0; k < bins; k++) {
+      const magnitude = Math.hypot(re[k], im[k]) / n;
+      this.smoothed[k] = this.smoothing * this.smoothed[k] + (1 - this.smoothing) * magnitude;
+      const decibels = 20 * Math.log10(this.smoothed[k] + 1e-12);
+      frequency[k] = Math.max(0, Math.min(255, Math.round((255 * (decibels - this.minDecibels)) / range)));
+    }
+
+    const bands = new Float32Array(this.bandCount), nyquist = sampleRate / 2;
+    for (let b = 0; b < this.bandCount; b++) {
+      const low = Math.floor((bins * 20 * Math.pow(nyquist / 20, b / this.bandCount)) / nyquist);
+      const high = Math.max(low + 1, Math.floor((bins * 20 * Math.pow(nyquist / 20, (b + 1) / this.bandCount)) / nyquist));
+      let sum = 0;
+      for (let k = low; k < high && k < bins; k++) sum += frequency[k];
+      bands[b] = sum / ((high - low) * 255);
+    }
+
+    this.port.postMessage({ time: currentTime, frequency, bands }, [frequency.buffer, bands.buffer]);
+  }
+}
+
+try {
+  registerProcessor("{name}", AudioVisualizerAnalyser);
+} catch (e) {}
+"#;
+
+#[derive(Default)]
+struct Frame {
+    time: f64,
+    frequency: Vec<u8>,
+    bands: Vec<f32>,
+}
+
+pub struct WorkletAnalyser {
+    node: AudioWorkletNode,
+    frame: Rc<RefCell<Frame>>,
+    _on_message: Closure<dyn FnMut(MessageEvent)>,
+}
+
+impl WorkletAnalyser {
+    pub async fn install(
+        context: &AudioContext,
+        analyser: &AnalyserNode,
+    ) -> Result<WorkletAnalyser, JsValue> {
+        let source = PROCESSOR_SOURCE.replace("{name}", PROCESSOR_NAME);
+        let parts = js_sys::Array::of1(&JsValue::from_str(&source));
+        let options = web_sys::BlobPropertyBag::new();
+        options.set_type("application/javascript");
+        let blob = web_sys::Blob::new_with_str_sequence_and_options(&parts, &options)?;
+        let url = web_sys::Url::create_object_url_with_blob(&blob)?;
+        let loaded = JsFuture::from(context.audio_worklet()?.add_module(&url)?).await;
+        let _ = web_sys::Url::revoke_object_url(&url);
+        loaded?;
+
+        let processor_options = js_sys::Object::new();
+        let settings: [(&str, JsValue); 6] = [
+            ("fftSize", analyser.fft_size().into()),
+            ("hopSize", HOP_SIZE.into()),
+            ("smoothing", analyser.smoothing_time_constant().into()),
+            ("minDecibels", analyser.min_decibels().into()),
+            ("maxDecibels", analyser.max_decibels().into()),
+            ("bands", BAND_COUNT.into()),
+        ];
+        for (key, value) in settings {
+            js_sys::Reflect::set(&processor_options, &key.into(), &value)?;
+        }
+
+        let node_options = AudioWorkletNodeOptions::new();
+        node_options.set_number_of_outputs(0);
+        node_options.set_processor_options(Some(&processor_options));
+        let node = AudioWorkletNode::new_with_options(context, PROCESSOR_NAME, &node_options)?;
+
+        let frame = Rc::new(RefCell::new(Frame::default()));
+        let on_message = {
+            let frame = frame.clone();
+            Closure::wrap(Box::new(move |event: MessageEvent| {
+                let data = event.data();
+                let field = |key: &str| js_sys::Reflect::get(&data, &key.into()).ok();
+                let mut frame = frame.borrow_mut();
+
+                if let Some(frequency) = field("frequency").and_then(|v| v.dyn_into::<js_sys::Uint8Array>().ok()) {
+                    frame.frequency.resize(frequency.length() as usize, 0);
+                    frequency.copy_to(&mut frame.frequency);
+                }
+                if let Some(bands) = field("bands").and_then(|v| v.dyn_into::<js_sys::Float32Array>().ok()) {
+                    frame.bands.resize(bands.length() as usize, 0.0);
+                    bands.copy_to(&mut frame.bands);
+                }
+                if let Some(time) = field("time").and_then(|v| v.as_f64()) {
+                    frame.time = time;
+                }
+            }) as Box<dyn FnMut(MessageEvent)>)
+        };
+        node.port()?.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+
+        analyser.connect_with_audio_node(&node)?;
+
+        Ok(WorkletAnalyser {
+            node,
+            frame,
+            _on_message: on_message,
+        })
+    }
+
+    pub fn read_frequency(&self, data: &mut Vec<u8>) {
+        let frame = self.frame.borrow();
+        if frame.frequency.is_empty() {
+            data.iter_mut().for_each(|value| *value = 0);
+        } else {
+            data.clear();
+            data.extend_from_slice(&frame.frequency);
+        }
+    }
+
+    pub fn bands(&self) -> Vec<f32> {
+        self.frame.borrow().bands.clone()
+    }
+
+    pub fn time(&self) -> f64 {
+        self.frame.borrow().time
+    }
+
+    pub fn disconnect(self, analyser: &AnalyserNode) {
+        let _ = analyser.disconnect_with_audio_node(&self.node);
+        if let Ok(port) = self.node.port() {
+            port.set_onmessage(None);
+            port.close();
+        }
+    }
+}